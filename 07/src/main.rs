@@ -12,7 +12,12 @@
 //   - || is an additional operator, that concatenates adjacent numbers
 //     (e.g. 12 || 34 becomes 1234)
 //   - sum up the results that can be calculated with the numbers and operators
+// - puzzle authoring:
+//   - count the operator assignments that produce the result
+//   - list all of them, or check that an equation has exactly one solution
 
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
@@ -33,8 +38,40 @@ fn main() {
     let sum = solver(&data, true);
     assert_eq!(sum, 124060392153684);
     println!("The sum of the valid expressions of part2 is {}", sum);
+
+    let data = read_data("input.test");
+    let (result, numbers) = parse_equation(&data[1]);
+    assert_eq!(count_solutions(result, &numbers, false), 2);
+    let equations = find_solutions(result, &numbers, false)
+        .iter()
+        .map(|ops| format_equation(&numbers, ops))
+        .collect::<Vec<String>>();
+    assert_eq!(equations, vec!["81 * 40 + 27", "81 + 40 * 27"]);
+    let (result, numbers) = parse_equation(&data[0]);
+    assert!(has_unique_solution(result, &numbers, false));
+    let (result, numbers) = parse_equation(&data[4]);
+    assert!(!has_unique_solution(result, &numbers, false));
+    assert!(has_unique_solution(result, &numbers, true));
+    assert_eq!(
+        find_solutions(result, &numbers, true),
+        vec![vec![
+            Operators::Multiply,
+            Operators::Concatenate,
+            Operators::Multiply
+        ]]
+    );
+
+    let data = read_data("input");
+    let (sum, assignments) = count_solver(&data, true);
+    assert_eq!(sum, 124060392153684);
+    assert_eq!(assignments, 592);
+    println!(
+        "The valid expressions of part2 have {} operator assignments",
+        assignments
+    );
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Operators {
     Add,
     Multiply,
@@ -48,24 +85,40 @@ impl Operators {
             Operators::Concatenate => lhs * 10i64.pow(rhs.ilog10() + 1) + rhs,
         }
     }
+    // get the left hand side that results in result when combined with rhs
+    fn undo(&self, result: i64, rhs: i64) -> Option<i64> {
+        match self {
+            Operators::Add => (result >= rhs).then(|| result - rhs),
+            Operators::Multiply => (rhs != 0 && result % rhs == 0).then(|| result / rhs),
+            Operators::Concatenate => {
+                let shift = 10i64.pow(rhs.ilog10() + 1);
+                (result >= rhs && (result - rhs) % shift == 0).then(|| (result - rhs) / shift)
+            }
+        }
+    }
+    fn all(part2: bool) -> Vec<Self> {
+        if part2 {
+            vec![Operators::Add, Operators::Multiply, Operators::Concatenate]
+        } else {
+            vec![Operators::Add, Operators::Multiply]
+        }
+    }
+}
+impl fmt::Display for Operators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operators::Add => write!(f, "+"),
+            Operators::Multiply => write!(f, "*"),
+            Operators::Concatenate => write!(f, "||"),
+        }
+    }
 }
 
 // solver for part 1 and 2
 fn solver(data: &Vec<String>, part2: bool) -> i64 {
     let mut sum = 0;
     for line in data {
-        let (result, numbers) = match line.split_once(':') {
-            Some(x) => {
-                let res = x.0.parse::<i64>().expect("Result couldn't be parsed");
-                let num =
-                    x.1.trim()
-                        .split(" ")
-                        .map(|x| x.parse::<i64>().expect("Number couldn't be parsed"))
-                        .collect::<Vec<i64>>();
-                (res, num)
-            }
-            None => panic!("No colon found in line"),
-        };
+        let (result, numbers) = parse_equation(line);
         if do_math(result, &numbers[1..], numbers[0], part2) {
             sum += result;
         }
@@ -73,6 +126,38 @@ fn solver(data: &Vec<String>, part2: bool) -> i64 {
     sum
 }
 
+// solver that counts the operator assignments, returns the sum of the valid
+// results and the total number of operator assignments
+fn count_solver(data: &[String], part2: bool) -> (i64, u64) {
+    let mut sum = 0;
+    let mut assignments = 0;
+    for line in data {
+        let (result, numbers) = parse_equation(line);
+        let count = count_solutions(result, &numbers, part2);
+        if count > 0 {
+            sum += result;
+        }
+        assignments += count;
+    }
+    (sum, assignments)
+}
+
+// split a line into the result and the numbers
+fn parse_equation(line: &str) -> (i64, Vec<i64>) {
+    match line.split_once(':') {
+        Some(x) => {
+            let res = x.0.parse::<i64>().expect("Result couldn't be parsed");
+            let num =
+                x.1.trim()
+                    .split(" ")
+                    .map(|x| x.parse::<i64>().expect("Number couldn't be parsed"))
+                    .collect::<Vec<i64>>();
+            (res, num)
+        }
+        None => panic!("No colon found in line"),
+    }
+}
+
 // recursive calculation function
 fn do_math(result: i64, numbers: &[i64], first: i64, part2: bool) -> bool {
     if numbers.is_empty() || first > result {
@@ -99,6 +184,96 @@ fn do_math(result: i64, numbers: &[i64], first: i64, part2: bool) -> bool {
         )
 }
 
+// count the operator assignments that calculate the result
+// - the operators are undone from the right, which prunes most branches early
+// - the memo maps (count of remaining numbers, intermediate result) to the
+//   number of assignments, so shared intermediate results are counted once
+// - the numbers need to be positive (as for do_math)
+fn count_solutions(result: i64, numbers: &[i64], part2: bool) -> u64 {
+    let mut memo = HashMap::new();
+    count_recursive(result, numbers, part2, &mut memo)
+}
+
+// recursive counting function
+fn count_recursive(
+    result: i64,
+    numbers: &[i64],
+    part2: bool,
+    memo: &mut HashMap<(usize, i64), u64>,
+) -> u64 {
+    let (last, rest) = numbers.split_last().expect("No numbers found");
+    if rest.is_empty() {
+        return (result == *last) as u64;
+    }
+    if let Some(count) = memo.get(&(rest.len(), result)) {
+        return *count;
+    }
+    let count = Operators::all(part2)
+        .iter()
+        .filter_map(|op| op.undo(result, *last))
+        .map(|lhs| count_recursive(lhs, rest, part2, memo))
+        .sum();
+    memo.insert((rest.len(), result), count);
+    count
+}
+
+// list all operator assignments that calculate the result, in left to right
+// order of the operators
+fn find_solutions(result: i64, numbers: &[i64], part2: bool) -> Vec<Vec<Operators>> {
+    let mut memo = HashMap::new();
+    let mut solutions = Vec::new();
+    collect_solutions(
+        result,
+        numbers,
+        part2,
+        &mut memo,
+        &mut Vec::new(),
+        &mut solutions,
+    );
+    solutions
+}
+
+// recursive collecting function, only follows branches with a solution
+fn collect_solutions(
+    result: i64,
+    numbers: &[i64],
+    part2: bool,
+    memo: &mut HashMap<(usize, i64), u64>,
+    operators: &mut Vec<Operators>,
+    solutions: &mut Vec<Vec<Operators>>,
+) {
+    let (last, rest) = numbers.split_last().expect("No numbers found");
+    if rest.is_empty() {
+        if result == *last {
+            solutions.push(operators.iter().rev().copied().collect());
+        }
+        return;
+    }
+    for op in Operators::all(part2) {
+        if let Some(lhs) = op.undo(result, *last) {
+            if count_recursive(lhs, rest, part2, memo) > 0 {
+                operators.push(op);
+                collect_solutions(lhs, rest, part2, memo, operators, solutions);
+                operators.pop();
+            }
+        }
+    }
+}
+
+// check that exactly one operator assignment calculates the result
+fn has_unique_solution(result: i64, numbers: &[i64], part2: bool) -> bool {
+    count_solutions(result, numbers, part2) == 1
+}
+
+// print an equation with its operators, e.g. 81 * 40 + 27
+fn format_equation(numbers: &[i64], operators: &[Operators]) -> String {
+    let mut equation = numbers[0].to_string();
+    numbers[1..].iter().zip(operators).for_each(|(number, op)| {
+        equation.push_str(&format!(" {} {}", op, number));
+    });
+    equation
+}
+
 // read a file with mathematical data and return as vector
 fn read_data(filename: &str) -> Vec<String> {
     let mut map = Vec::new();
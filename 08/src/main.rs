@@ -16,10 +16,16 @@
 //     pair of antennas (at the same distance between the antennas)
 //   - count the number of antinodes
 //     - also the antenna positions count as antinodes
+//     - all positions exactly in line with two antennas count, also the ones
+//       between them
+// - report:
+//   - print the map with the antinodes
+//   - count the antennas, antinodes and shared antinodes of each frequency
+//   - run with `report [map file]`
 
 use std::collections::HashMap;
 use std::fmt;
-use std::fs::File;
+use std::fs::{read_to_string, File};
 use std::io::{self, BufRead};
 use std::path::Path;
 
@@ -39,6 +45,37 @@ fn main() {
     let sum_antinodes = part2(&map);
     assert_eq!(sum_antinodes, 766);
     println!("The antennas create {} harmonic antinodes", sum_antinodes);
+
+    let map = read_data("input2.test");
+    assert_eq!(part1(&map), 1);
+    assert_eq!(part2(&map), 6);
+    assert_eq!(count_antinodes(&map, Harmonics::First(0)), 3);
+    assert_eq!(count_antinodes(&map, Harmonics::First(1)), 5);
+    assert_eq!(count_antinodes(&map, Harmonics::Within(1)), 2);
+    assert_eq!(count_antinodes(&map, Harmonics::Within(2)), 5);
+    let positions = read_map(&map);
+    let antinodes = find_antinodes(&positions, map[0].len(), map.len(), Harmonics::Fundamental);
+    assert!(antinodes
+        .iter()
+        .all(|a| a.frequency == 'a' && a.antennas == (Point::new(1, 1), Point::new(3, 3))));
+
+    let map = read_data("input.test");
+    let rendered = read_to_string("input.test").expect("File not found");
+    assert_eq!(render_map(&map, Harmonics::Fundamental), rendered);
+    let stats = frequency_stats(&map, Harmonics::Fundamental);
+    assert_eq!(
+        stats,
+        vec![
+            FrequencyStats::new('0', 4, 10, 1),
+            FrequencyStats::new('A', 3, 5, 1)
+        ]
+    );
+
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(|a| a.as_str()) == Some("report") {
+        let map = read_data(args.get(2).map_or("input", |a| a.as_str()));
+        print_report(&map, Harmonics::All);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -89,25 +126,22 @@ impl fmt::Display for Point {
 
 // solver for part 1
 fn part1(map: &[Vec<char>]) -> usize {
-    let positions = read_map(map);
-    let antinodes = find_antinodes(&positions, map[0].len(), map.len(), false);
-    let mut all_antinodes = Vec::new();
-    antinodes.values().for_each(|vals| {
-        all_antinodes.append(&mut vals.clone());
-    });
-    all_antinodes.sort();
-    all_antinodes.dedup();
-    all_antinodes.len()
+    count_antinodes(map, Harmonics::Fundamental)
 }
 
 // solver for part 2
 fn part2(map: &[Vec<char>]) -> usize {
+    count_antinodes(map, Harmonics::All)
+}
+
+// count the unique antinode positions
+fn count_antinodes(map: &[Vec<char>], harmonics: Harmonics) -> usize {
     let positions = read_map(map);
-    let antinodes = find_antinodes(&positions, map[0].len(), map.len(), true);
-    let mut all_antinodes = Vec::new();
-    antinodes.values().for_each(|vals| {
-        all_antinodes.append(&mut vals.clone());
-    });
+    let antinodes = find_antinodes(&positions, map[0].len(), map.len(), harmonics);
+    let mut all_antinodes = antinodes
+        .into_iter()
+        .map(|a| a.position)
+        .collect::<Vec<Point>>();
     all_antinodes.sort();
     all_antinodes.dedup();
     all_antinodes.len()
@@ -138,60 +172,193 @@ fn read_map(map: &[Vec<char>]) -> HashMap<char, Vec<Point>> {
     positions
 }
 
+// which lattice points on the line through two antennas are antinodes
+// - the points are counted in steps of the antenna distance reduced by its
+//   gcd, so no lattice point on the line is skipped
+#[derive(Debug, Clone, Copy)]
+enum Harmonics {
+    // part 1: the points at the antenna distance before/behind the antennas
+    Fundamental,
+    // part 2: all points on the line
+    All,
+    // the antennas, the points between them and the first n points
+    // before/behind the antennas
+    First(i64),
+    // the points, that are within the given distance to one of the antennas
+    Within(i64),
+}
+impl Harmonics {
+    // get the multiples of the step, starting at the first antenna, where
+    // the second antenna is at multiple gcd
+    fn multiples(&self, gcd: i64, step: (i64, i64), size: i64) -> Vec<i64> {
+        match self {
+            Harmonics::Fundamental => vec![-gcd, 2 * gcd],
+            Harmonics::All => (-size..=gcd + size).collect(),
+            Harmonics::First(n) => (-n..=gcd + n).collect(),
+            Harmonics::Within(distance) => {
+                let step_length = step.0 * step.0 + step.1 * step.1;
+                let mut n = 0;
+                while (n + 1) * (n + 1) * step_length <= distance * distance {
+                    n += 1;
+                }
+                (-n..=gcd + n)
+                    .filter(|m| {
+                        let nearest = m.abs().min((m - gcd).abs());
+                        nearest * nearest * step_length <= distance * distance
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// an antinode with the frequency and the antennas that created it
+#[derive(Debug, Clone)]
+struct Antinode {
+    position: Point,
+    frequency: char,
+    antennas: (Point, Point),
+}
+
 // find all antinodes for each antenna combination
 fn find_antinodes(
     antennas: &HashMap<char, Vec<Point>>,
     x: usize,
     y: usize,
-    part2: bool,
-) -> HashMap<char, Vec<Point>> {
-    let mut antinodes: HashMap<char, Vec<Point>> = HashMap::new();
-    antennas.keys().for_each(|key| {
-        let positions = antennas.get(key).unwrap();
+    harmonics: Harmonics,
+) -> Vec<Antinode> {
+    let mut antinodes = Vec::new();
+    let mut frequencies = antennas.keys().collect::<Vec<&char>>();
+    frequencies.sort();
+    for frequency in frequencies {
+        let positions = antennas.get(frequency).unwrap();
         (0..positions.len()).for_each(|first| {
-            (0..positions.len()).for_each(|second| {
-                if first != second {
-                    // as we're trying all point combinations, we only have to
-                    // worry about one point, the other one will be handled when
-                    // the points are reversed
-                    let (dx, dy) = &positions[second].distance(&positions[first]);
-                    // add the antenna positions as antinodes for part 2
-                    if part2 {
-                        if antinodes.contains_key(key) {
-                            antinodes
-                                .get_mut(key)
-                                .unwrap()
-                                .push(positions[first].clone());
-                            antinodes
-                                .get_mut(key)
-                                .unwrap()
-                                .push(positions[second].clone());
-                        } else {
-                            antinodes.insert(
-                                *key,
-                                vec![positions[first].clone(), positions[second].clone()],
-                            );
-                        }
-                    }
-                    let mut p = positions[second].clone();
-                    while p.with_delta(*dx, *dy, x, y).is_some() {
-                        p = p.with_delta(*dx, *dy, x, y).unwrap();
-                        if antinodes.contains_key(key) {
-                            antinodes.get_mut(key).unwrap().push(p.clone());
-                        } else {
-                            antinodes.insert(*key, vec![p.clone()]);
-                        }
-                        if !part2 {
-                            break;
-                        }
+            (first + 1..positions.len()).for_each(|second| {
+                let (dx, dy) = positions[second].distance(&positions[first]);
+                let gcd = gcd(dx.abs(), dy.abs());
+                let step = (dx / gcd, dy / gcd);
+                let size = x.max(y) as i64;
+                for n in harmonics.multiples(gcd, step, size) {
+                    if let Some(p) = positions[first].with_delta(n * step.0, n * step.1, x, y) {
+                        antinodes.push(Antinode {
+                            position: p,
+                            frequency: *frequency,
+                            antennas: (positions[first].clone(), positions[second].clone()),
+                        });
                     }
                 }
             })
         })
-    });
+    }
     antinodes
 }
 
+// greatest common divisor
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// antenna and antinode statistics of a frequency
+#[derive(Debug, PartialEq)]
+struct FrequencyStats {
+    frequency: char,
+    antennas: usize,
+    antinodes: usize,
+    // antinodes that are also antinodes of another frequency
+    shared: usize,
+}
+impl FrequencyStats {
+    fn new(frequency: char, antennas: usize, antinodes: usize, shared: usize) -> Self {
+        Self {
+            frequency,
+            antennas,
+            antinodes,
+            shared,
+        }
+    }
+}
+
+// get the unique antinode positions of each frequency
+fn antinodes_by_frequency(
+    map: &[Vec<char>],
+    harmonics: Harmonics,
+) -> (HashMap<char, Vec<Point>>, HashMap<char, Vec<Point>>) {
+    let positions = read_map(map);
+    let mut antinodes: HashMap<char, Vec<Point>> = HashMap::new();
+    for antinode in find_antinodes(&positions, map[0].len(), map.len(), harmonics) {
+        antinodes
+            .entry(antinode.frequency)
+            .or_default()
+            .push(antinode.position);
+    }
+    antinodes.values_mut().for_each(|vals| {
+        vals.sort();
+        vals.dedup();
+    });
+    (positions, antinodes)
+}
+
+// collect the antenna and antinode statistics for each frequency
+fn frequency_stats(map: &[Vec<char>], harmonics: Harmonics) -> Vec<FrequencyStats> {
+    let (positions, antinodes) = antinodes_by_frequency(map, harmonics);
+    let mut frequencies = positions.keys().collect::<Vec<&char>>();
+    frequencies.sort();
+    frequencies
+        .into_iter()
+        .map(|frequency| {
+            let own = antinodes.get(frequency).cloned().unwrap_or_default();
+            let shared = own
+                .iter()
+                .filter(|p| {
+                    antinodes
+                        .iter()
+                        .any(|(other, vals)| other != frequency && vals.binary_search(p).is_ok())
+                })
+                .count();
+            FrequencyStats::new(
+                *frequency,
+                positions.get(frequency).unwrap().len(),
+                own.len(),
+                shared,
+            )
+        })
+        .collect()
+}
+
+// draw the map with the antinodes (#), antennas are drawn on top
+fn render_map(map: &[Vec<char>], harmonics: Harmonics) -> String {
+    let (positions, antinodes) = antinodes_by_frequency(map, harmonics);
+    let mut rendered = vec![vec!['.'; map[0].len()]; map.len()];
+    antinodes.values().flatten().for_each(|p| {
+        rendered[p.y][p.x] = '#';
+    });
+    positions.iter().for_each(|(frequency, vals)| {
+        vals.iter().for_each(|p| {
+            rendered[p.y][p.x] = *frequency;
+        })
+    });
+    rendered
+        .into_iter()
+        .map(|line| line.into_iter().collect::<String>() + "\n")
+        .collect()
+}
+
+// print the map with the antinodes and the statistics of each frequency
+fn print_report(map: &[Vec<char>], harmonics: Harmonics) {
+    print!("{}", render_map(map, harmonics));
+    println!("frequency antennas antinodes shared");
+    for stats in frequency_stats(map, harmonics) {
+        println!(
+            "{:>9} {:>8} {:>9} {:>6}",
+            stats.frequency, stats.antennas, stats.antinodes, stats.shared
+        );
+    }
+}
+
 // read a map file
 fn read_data(filename: &str) -> Vec<Vec<char>> {
     let mut map = Vec::new();
@@ -20,6 +20,17 @@
 //   - defragment the disk by moving the rightmost file to the left most
 //     bin of free blocks (that can hold the file)
 //   - calculate the new file system checksum as in part 1
+//   - the disk is stored as file extents and the free space is indexed by
+//     size, so defragmenting scales to large disk maps
+// - strategies:
+//   - compare defragmentation strategies (first/best/worst fit, block based
+//     and minimal moves) by moved blocks, moves, fragmentation and checksum
+// - dumps:
+//   - serialise a disk back to the disk map format
+//   - dump the blocks like 00...111...2 or run-length encoded
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
 
 fn main() {
     let disk_map = read_data("input.test");
@@ -36,17 +47,93 @@ fn main() {
     println!("The new disk checksum is {}", checksum);
 
     let disk_map = read_data("input.test");
-    let mut disk_layout = generate_layout(&disk_map);
-    defragment_disk_2(&mut disk_layout);
-    let checksum = calculate_checksum(&disk_layout);
-    assert_eq!(checksum, 2858);
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_files(Fit::First);
+    assert_eq!(disk.checksum(), 2858);
+    assert_eq!(calculate_checksum(&disk.layout()), 2858);
 
     let disk_map = read_data("input");
-    let mut disk_layout = generate_layout(&disk_map);
-    defragment_disk_2(&mut disk_layout);
-    let checksum = calculate_checksum(&disk_layout);
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_files(Fit::First);
+    let checksum = disk.checksum();
     assert_eq!(checksum, 6272188244509);
     println!("The new file optimized disk checksum is {}", checksum);
+
+    // the input repeated 100 times has two million entries
+    let disk_map = read_data("input").repeat(100);
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_files(Fit::First);
+    let checksum = disk.checksum();
+    assert_eq!(checksum, 5591049096076856695);
+    println!("The large file optimized disk checksum is {}", checksum);
+
+    // with three million entries the checksum doesn't fit an i64 anymore
+    let disk_map = read_data("input").repeat(150);
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_files(Fit::First);
+    let checksum = disk.checksum();
+    assert_eq!(checksum, calculate_checksum(&disk.layout()));
+    assert_eq!(checksum, 18868919717651717336);
+    println!("The larger file optimized disk checksum is {}", checksum);
+
+    let strategies: Vec<Box<dyn Strategy>> = vec![
+        Box::new(FirstFitSplit),
+        Box::new(FirstFit),
+        Box::new(BestFit),
+        Box::new(WorstFit),
+        Box::new(MinimalMoves),
+    ];
+    let disk_map = read_data("input.test");
+    let reports = strategies
+        .iter()
+        .map(|s| run_strategy(&disk_map, s.as_ref()))
+        .collect::<Vec<Report>>();
+    assert_eq!(reports[0].checksum, 1928);
+    assert_eq!(reports[1].checksum, 2858);
+    assert_eq!(reports[0].fragmentation, 0);
+    assert_eq!(reports[4].fragmentation, 0);
+    assert_eq!(reports[0].blocks_moved, reports[4].blocks_moved);
+
+    let disk_map = read_data("input");
+    let reports = strategies
+        .iter()
+        .map(|s| run_strategy(&disk_map, s.as_ref()))
+        .collect::<Vec<Report>>();
+    assert_eq!(reports[0].checksum, 6242766523059);
+    assert_eq!(reports[1].checksum, 6272188244509);
+    println!("strategy         blocks  moves  fragmentation  checksum");
+    for report in reports {
+        println!(
+            "{:<16} {:>6} {:>6} {:>14}  {}",
+            report.name, report.blocks_moved, report.moves, report.fragmentation, report.checksum
+        );
+    }
+
+    let disk_map = read_data("input.test");
+    let disk = Disk::new(&disk_map);
+    assert_eq!(disk.disk_map(), "2333133121414131402");
+    assert_eq!(disk.dump(), "00...111...2...333.44.5555.6666.777.888899");
+    assert_eq!(
+        disk.dump_runs(),
+        "0*2 .*3 1*3 .*3 2*1 .*3 3*3 .*1 4*2 .*1 5*4 .*1 6*4 .*1 7*3 .*1 8*4 9*2"
+    );
+    let states = std::fs::read_to_string("states.test").expect("Can't read states");
+    assert_eq!(
+        replay(&disk_map, &FirstFit),
+        states.lines().collect::<Vec<&str>>()
+    );
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_files(Fit::First);
+    assert_eq!(disk.disk_map(), "20201030312134414542");
+    let disk = Disk::new(&parse_disk_map(&disk.disk_map()));
+    assert_eq!(disk.dump(), "00112333444.55.666....7777.8888.....9999..");
+
+    let disk_map = read_data("input");
+    let mut disk = Disk::new(&disk_map);
+    disk.compact_blocks();
+    let round_trip = Disk::new(&parse_disk_map(&disk.disk_map()));
+    assert_eq!(disk.disk_map(), round_trip.disk_map());
+    assert_eq!(round_trip.layout().len(), disk.layout().len());
 }
 
 // generate the disk layout from the disk_map
@@ -104,84 +191,441 @@ fn is_defragmented(disk_layout: &[i64]) -> bool {
     defragmented
 }
 
-// calculate the file system checksum
-fn calculate_checksum(disk_layout: &[i64]) -> i64 {
+// calculate the file system checksum, it doesn't fit an i64 for disk maps
+// with millions of entries
+fn calculate_checksum(disk_layout: &[i64]) -> u128 {
     let mut sum = 0;
     for (idx, val) in disk_layout.iter().enumerate() {
         if *val > -1 {
-            sum += (idx as i64) * val;
+            sum += idx as u128 * *val as u128;
         }
     }
     sum
 }
 
-// file based defragmenting
-fn defragment_disk_2(disk_layout: &mut [i64]) {
-    let last = disk_layout.last().expect("No last element");
-    for file_id in (0..=*last).rev() {
-        let (file_start, file_size) = get_file_block_data(file_id, disk_layout);
-        if let Some(free_start) = find_free_block_bin(file_size, disk_layout) {
-            if free_start < file_start {
-                for idx in 0..file_size {
-                    disk_layout[free_start + idx] = disk_layout[file_start + idx];
-                    disk_layout[file_start + idx] = -1;
+// a contiguous run of blocks that belong to a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Extent {
+    file_id: usize,
+    start: usize,
+    size: usize,
+}
+impl Extent {
+    fn new(file_id: usize, start: usize, size: usize) -> Self {
+        Self {
+            file_id,
+            start,
+            size,
+        }
+    }
+    fn end(&self) -> usize {
+        self.start + self.size
+    }
+}
+
+// extent based disk model
+// - only the files are stored, the free space is the gap between them
+#[derive(Debug, Clone)]
+struct Disk {
+    files: Vec<Extent>,
+    size: usize,
+}
+impl Disk {
+    // generate the disk from the disk_map
+    fn new(disk_map: &[i64]) -> Self {
+        let mut files = Vec::new();
+        let mut start = 0;
+        (0..disk_map.len()).for_each(|index| {
+            let size = disk_map[index] as usize;
+            if index % 2 == 0 && size > 0 {
+                files.push(Extent::new(index / 2, start, size));
+            }
+            start += size;
+        });
+        Self { files, size: start }
+    }
+    // get the free extents as (start, size), ordered by start
+    fn free_extents(&self) -> Vec<(usize, usize)> {
+        let mut files = self.files.clone();
+        files.sort_by_key(|f| f.start);
+        let mut free = Vec::new();
+        let mut start = 0;
+        for file in files {
+            if file.start > start {
+                free.push((start, file.start - start));
+            }
+            start = file.end();
+        }
+        if self.size > start {
+            free.push((start, self.size - start));
+        }
+        free
+    }
+    // file based defragmenting
+    // - the files are moved right to left to a free extent left of them,
+    //   that can hold them, the fit decides which one
+    // - the free extents are indexed by one min-heap of start positions per
+    //   size, so finding a fitting extent takes one look at each heap top
+    // - space freed by a moved file is never used, as all remaining files
+    //   are left of it
+    fn compact_files(&mut self, fit: Fit) -> Vec<Move> {
+        let free = self.free_extents();
+        let max_size = free.iter().map(|f| f.1).max().unwrap_or(0);
+        let mut heaps = vec![BinaryHeap::new(); max_size + 1];
+        for (start, size) in free {
+            heaps[size].push(Reverse(start));
+        }
+        let mut moves = Vec::new();
+        self.files
+            .sort_by(|a, b| b.file_id.cmp(&a.file_id).then(b.start.cmp(&a.start)));
+        for file in self.files.iter_mut() {
+            let mut candidates = (file.size..=max_size)
+                .filter_map(|size| heaps[size].peek().map(|Reverse(start)| (*start, size)))
+                .filter(|(start, _)| *start < file.start);
+            let chosen = match fit {
+                Fit::First => candidates.min(),
+                Fit::Best => candidates.next(),
+                Fit::Worst => candidates.next_back(),
+            };
+            if let Some((start, size)) = chosen {
+                heaps[size].pop();
+                moves.push(Move::new(file.file_id, file.start, start, file.size));
+                file.start = start;
+                if size > file.size {
+                    heaps[size - file.size].push(Reverse(start + file.size));
+                }
+            }
+        }
+        self.files.sort_by_key(|f| f.file_id);
+        moves
+    }
+    // block based defragmenting
+    // - the rightmost file blocks are moved to the leftmost free extent, the
+    //   files are split if the free extent is too small
+    fn compact_blocks(&mut self) -> Vec<Move> {
+        let mut free = self.free_extents();
+        free.reverse();
+        let mut files = self.files.clone();
+        files.sort_by_key(|f| f.start);
+        let mut moves = Vec::new();
+        let mut compacted = Vec::new();
+        while let Some(mut file) = files.pop() {
+            while let Some((start, size)) = free.pop() {
+                if start >= file.start {
+                    free.clear();
+                    break;
+                }
+                let blocks = size.min(file.size);
+                file.size -= blocks;
+                moves.push(Move::new(file.file_id, file.end(), start, blocks));
+                compacted.push(Extent::new(file.file_id, start, blocks));
+                if size > blocks {
+                    free.push((start + blocks, size - blocks));
+                }
+                if file.size == 0 {
+                    break;
+                }
+            }
+            if file.size > 0 {
+                compacted.push(file);
+            }
+        }
+        compacted.sort_by(|a, b| a.file_id.cmp(&b.file_id).then(a.start.cmp(&b.start)));
+        self.files = compacted;
+        moves
+    }
+    // block based defragmenting, that moves each block at most once
+    // - only the blocks behind the used size have to be moved, into the free
+    //   extents in front of it
+    // - the largest pieces are placed first, into a free extent of the same
+    //   size, the smallest larger one or split over the largest one, to keep
+    //   the number of moves low
+    fn compact_minimal(&mut self) -> Vec<Move> {
+        let used = self.files.iter().map(|f| f.size).sum::<usize>();
+        let mut gaps: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (start, size) in self.free_extents() {
+            if start < used {
+                gaps.entry(size.min(used - start)).or_default().push(start);
+            }
+        }
+        let mut compacted = Vec::new();
+        let mut pieces = Vec::new();
+        for file in &self.files {
+            if file.end() <= used {
+                compacted.push(*file);
+            } else if file.start >= used {
+                pieces.push(*file);
+            } else {
+                compacted.push(Extent::new(file.file_id, file.start, used - file.start));
+                pieces.push(Extent::new(file.file_id, used, file.end() - used));
+            }
+        }
+        pieces.sort_by(|a, b| b.size.cmp(&a.size).then(a.start.cmp(&b.start)));
+        let mut moves = Vec::new();
+        for mut piece in pieces {
+            while piece.size > 0 {
+                let size = match gaps.range(piece.size..).next() {
+                    Some((size, _)) => *size,
+                    None => *gaps.keys().last().expect("No free space left"),
+                };
+                let starts = gaps.get_mut(&size).unwrap();
+                let start = starts.pop().unwrap();
+                if starts.is_empty() {
+                    gaps.remove(&size);
+                }
+                let blocks = size.min(piece.size);
+                moves.push(Move::new(piece.file_id, piece.start, start, blocks));
+                compacted.push(Extent::new(piece.file_id, start, blocks));
+                piece.start += blocks;
+                piece.size -= blocks;
+                if size > blocks {
+                    gaps.entry(size - blocks).or_default().push(start + blocks);
                 }
             }
         }
+        compacted.sort_by(|a, b| a.file_id.cmp(&b.file_id).then(a.start.cmp(&b.start)));
+        self.files = compacted;
+        moves
+    }
+    // count the free extents between the files
+    fn fragmentation(&self) -> usize {
+        let last = self.files.iter().map(|f| f.end()).max().unwrap_or(0);
+        self.free_extents()
+            .iter()
+            .filter(|(start, _)| *start < last)
+            .count()
+    }
+    // calculate the file system checksum
+    fn checksum(&self) -> u128 {
+        self.files
+            .iter()
+            .map(|f| {
+                let blocks = f.start * f.size + f.size * (f.size - 1) / 2;
+                f.file_id as u128 * blocks as u128
+            })
+            .sum()
+    }
+    // expand the disk to the block layout
+    fn layout(&self) -> Vec<i64> {
+        let mut layout = vec![-1; self.size];
+        for file in &self.files {
+            (file.start..file.end()).for_each(|idx| layout[idx] = file.file_id as i64);
+        }
+        layout
+    }
+    // get the files and free space as (file ID, size) runs, ordered by
+    // position, adjacent extents of the same file are merged
+    fn runs(&self) -> Vec<(Option<usize>, usize)> {
+        let mut files = self.files.clone();
+        files.sort_by_key(|f| f.start);
+        let mut runs: Vec<(Option<usize>, usize)> = Vec::new();
+        let mut start = 0;
+        for file in files {
+            if file.start > start {
+                runs.push((None, file.start - start));
+            }
+            match runs.last_mut() {
+                Some((Some(id), size)) if *id == file.file_id && file.start == start => {
+                    *size += file.size
+                }
+                _ => runs.push((Some(file.file_id), file.size)),
+            }
+            start = file.end();
+        }
+        if self.size > start {
+            runs.push((None, self.size - start));
+        }
+        runs
+    }
+    // serialise the disk to the dense disk map format
+    // - the files are renumbered by position
+    // - sizes above 9 are split up with empty files or free space
+    fn disk_map(&self) -> String {
+        let mut entries = Vec::new();
+        for (id, mut size) in self.runs() {
+            while size > 0 {
+                // even entries are files, odd entries are free space
+                if (entries.len() % 2 == 0) != id.is_some() {
+                    entries.push(0);
+                }
+                let blocks = size.min(9);
+                entries.push(blocks);
+                size -= blocks;
+            }
+        }
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+    // dump the blocks like 00...111...2, files are shown with the last digit
+    // of their ID
+    fn dump(&self) -> String {
+        dump_layout(&self.layout())
+    }
+    // dump the blocks run-length encoded like 0*2 .*3 1*3 .*3 2*1, to keep
+    // the dump of large disks short
+    fn dump_runs(&self) -> String {
+        self.runs()
+            .iter()
+            .map(|(id, size)| match id {
+                Some(id) => format!("{}*{}", id, size),
+                None => format!(".*{}", size),
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
     }
 }
 
-// get start and size of file block for file ID
-fn get_file_block_data(file_id: i64, disk_layout: &[i64]) -> (usize, usize) {
-    let file_start = disk_layout
+// dump a block layout like 00...111...2
+fn dump_layout(disk_layout: &[i64]) -> String {
+    disk_layout
         .iter()
-        .position(|&e| e == file_id)
-        .expect("File ID start not found");
-    let file_end = disk_layout.len()
-        - disk_layout
-            .iter()
-            .rev()
-            .position(|&e| e == file_id)
-            .expect("File ID end not found")
-        - 1;
-    let file_size = file_end - file_start + 1;
-    (file_start, file_size)
-}
-
-// try to find a bin of free blocks with size
-fn find_free_block_bin(size: usize, disk_layout: &[i64]) -> Option<usize> {
-    let mut skip = 0;
-    let mut free_start: usize = 0;
-    let mut free_end: usize;
-    let mut free_size: usize = 0;
-    let mut valid = true;
-    while free_size < size {
-        if let Some(e) = disk_layout.iter().skip(skip).position(|&e| e == -1) {
-            free_start = e + skip;
-        } else {
-            valid = false;
-            break;
-        };
-        if let Some(e) = disk_layout.iter().skip(free_start).position(|&e| e > -1) {
-            free_end = e + free_start;
-        } else {
-            valid = false;
-            break;
+        .map(|&e| {
+            if e > -1 {
+                char::from_digit((e % 10) as u32, 10).unwrap()
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+// dump the block layout after each move of a strategy
+fn replay(disk_map: &[i64], strategy: &dyn Strategy) -> Vec<String> {
+    let mut disk = Disk::new(disk_map);
+    let mut disk_layout = disk.layout();
+    let mut states = vec![dump_layout(&disk_layout)];
+    for m in strategy.defragment(&mut disk) {
+        for idx in 0..m.size {
+            disk_layout[m.to + idx] = disk_layout[m.from + idx];
+            disk_layout[m.from + idx] = -1;
+        }
+        states.push(dump_layout(&disk_layout));
+    }
+    states
+}
+
+// which free extent a file is moved to
+#[derive(Debug, Clone, Copy)]
+enum Fit {
+    // the leftmost one
+    First,
+    // the smallest one
+    Best,
+    // the largest one
+    Worst,
+}
+
+// blocks of a file moved from one position to another
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Move {
+    file_id: usize,
+    from: usize,
+    to: usize,
+    size: usize,
+}
+impl Move {
+    fn new(file_id: usize, from: usize, to: usize, size: usize) -> Self {
+        Self {
+            file_id,
+            from,
+            to,
+            size,
         }
-        skip = free_end;
-        free_size = free_end - free_start;
     }
-    if valid {
-        Some(free_start)
-    } else {
-        None
+}
+
+// a defragmentation strategy
+trait Strategy {
+    fn name(&self) -> &'static str;
+    // defragment the disk and return the moves done
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move>;
+}
+
+// part 1: move blocks to the leftmost free extent, split files if needed
+struct FirstFitSplit;
+impl Strategy for FirstFitSplit {
+    fn name(&self) -> &'static str {
+        "first fit split"
+    }
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move> {
+        disk.compact_blocks()
+    }
+}
+
+// part 2: move files to the leftmost free extent that can hold them
+struct FirstFit;
+impl Strategy for FirstFit {
+    fn name(&self) -> &'static str {
+        "first fit"
+    }
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move> {
+        disk.compact_files(Fit::First)
+    }
+}
+
+// move files to the smallest free extent that can hold them
+struct BestFit;
+impl Strategy for BestFit {
+    fn name(&self) -> &'static str {
+        "best fit"
+    }
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move> {
+        disk.compact_files(Fit::Best)
+    }
+}
+
+// move files to the largest free extent
+struct WorstFit;
+impl Strategy for WorstFit {
+    fn name(&self) -> &'static str {
+        "worst fit"
+    }
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move> {
+        disk.compact_files(Fit::Worst)
+    }
+}
+
+// compact the disk with as few moved blocks and moves as possible
+struct MinimalMoves;
+impl Strategy for MinimalMoves {
+    fn name(&self) -> &'static str {
+        "minimal moves"
+    }
+    fn defragment(&self, disk: &mut Disk) -> Vec<Move> {
+        disk.compact_minimal()
+    }
+}
+
+// cost and result of a defragmentation run
+#[derive(Debug)]
+struct Report {
+    name: &'static str,
+    blocks_moved: usize,
+    moves: usize,
+    fragmentation: usize,
+    checksum: u128,
+}
+
+// defragment the disk map with a strategy and report the costs
+fn run_strategy(disk_map: &[i64], strategy: &dyn Strategy) -> Report {
+    let mut disk = Disk::new(disk_map);
+    let moves = strategy.defragment(&mut disk);
+    Report {
+        name: strategy.name(),
+        blocks_moved: moves.iter().map(|m| m.size).sum(),
+        moves: moves.len(),
+        fragmentation: disk.fragmentation(),
+        checksum: calculate_checksum(&disk.layout()),
     }
 }
 
 // read a disk map file
 fn read_data(filename: &str) -> Vec<i64> {
-    std::fs::read_to_string(filename)
-        .expect("Can't read input")
+    parse_disk_map(&std::fs::read_to_string(filename).expect("Can't read input"))
+}
+
+// parse a disk map
+fn parse_disk_map(disk_map: &str) -> Vec<i64> {
+    disk_map
         .trim()
         .chars()
         .map(|c| c.to_string().parse::<i64>().expect("Can't parse number"))
@@ -5,6 +5,7 @@
 //   - 9 is the top and is the end
 //   - while walking, you can only climb a difference of 1 and only upwards
 //   - only left, down, up and right walking is allowed (not diagonal)
+//   - the map has rows and columns and doesn't need to be square
 // - part 1:
 //   - the score of a trail head marks the amount of tops you can reach from it
 //     (there might be multiple ways to this top, but it only counts once)
@@ -12,97 +13,96 @@
 // - part 2:
 //   - the rating of a trail head marks the amount of unique ways to a top it has
 //   - calculate the trail head ratings and sum them up
+// - scores and ratings are calculated for all trail heads at once, from the
+//   tops downwards, the trails themselves can be listed on request
+// - the heights of trail heads and tops and the allowed height difference
+//   of a step can be configured for other topographic maps
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 
 fn main() {
+    let rules = Rules::puzzle();
     let map = read_data("input.test");
-    let trail_heads = get_trail_heads(&map);
+    let trail_heads = get_trail_heads(&map, &rules);
     assert_eq!(trail_heads.len(), 9);
-    let sum = assess_trail_heads(trail_heads, &map, false);
+    let sum = assess_trail_heads(trail_heads, &map, &rules, false);
     assert_eq!(sum, 36);
 
     let map = read_data("input");
-    let trail_heads = get_trail_heads(&map);
-    let sum = assess_trail_heads(trail_heads, &map, false);
+    let trail_heads = get_trail_heads(&map, &rules);
+    let sum = assess_trail_heads(trail_heads, &map, &rules, false);
     assert_eq!(sum, 550);
     println!("The trail head score sum is {}", sum);
 
     let map = read_data("input.test");
-    let trail_heads = get_trail_heads(&map);
+    let trail_heads = get_trail_heads(&map, &rules);
     assert_eq!(trail_heads.len(), 9);
-    let sum = assess_trail_heads(trail_heads, &map, true);
+    let sum = assess_trail_heads(trail_heads, &map, &rules, true);
     assert_eq!(sum, 81);
 
     let map = read_data("input");
-    let trail_heads = get_trail_heads(&map);
-    let sum = assess_trail_heads(trail_heads, &map, true);
+    let trail_heads = get_trail_heads(&map, &rules);
+    let sum = assess_trail_heads(trail_heads, &map, &rules, true);
     assert_eq!(sum, 1255);
     println!("The trail head rating sum is {}", sum);
+
+    let map = read_data("input.test");
+    let trail_heads = get_trail_heads(&map, &rules);
+    let trails = trail_heads
+        .iter()
+        .map(|point| find_trails(point, &map, &rules))
+        .collect::<Vec<Vec<Vec<(usize, usize)>>>>();
+    assert_eq!(trails.iter().map(|t| t.len()).sum::<usize>(), 81);
+    assert!(trails.iter().zip(&trail_heads).all(|(t, head)| {
+        t.iter()
+            .all(|trail| trail.len() == 10 && trail[0] == (head.row, head.col))
+    }));
+    // a map with more columns than rows
+    let map = read_data("input2.test");
+    assert_eq!((map.rows, map.cols), (4, 10));
+    let trail_heads = get_trail_heads(&map, &rules);
+    let score = assess_trail_heads(trail_heads.clone(), &map, &rules, false);
+    let rating = assess_trail_heads(trail_heads, &map, &rules, true);
+    assert_eq!((score, rating), (5, 38));
+    let steep = Rules::new(0, 9, 1, 2);
+    let trail_heads = get_trail_heads(&map, &steep);
+    let score = assess_trail_heads(trail_heads.clone(), &map, &steep, false);
+    let rating = assess_trail_heads(trail_heads, &map, &steep, true);
+    assert_eq!((score, rating), (6, 41));
+    let hiking = Rules::new(0, 9, -1, 1);
+    let trail_heads = get_trail_heads(&map, &hiking);
+    let score = assess_trail_heads(trail_heads.clone(), &map, &hiking, false);
+    let rating = trail_heads
+        .iter()
+        .map(|point| find_trails(point, &map, &hiking).len())
+        .sum::<usize>();
+    assert_eq!((score, rating), (8, 2025));
+    let short = Rules::new(3, 5, 1, 1);
+    let trail_heads = get_trail_heads(&map, &short);
+    let score = assess_trail_heads(trail_heads.clone(), &map, &short, false);
+    let rating = assess_trail_heads(trail_heads, &map, &short, true);
+    assert_eq!((score, rating), (4, 5));
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 struct Point {
-    x: usize,
-    y: usize,
+    row: usize,
+    col: usize,
     val: usize,
 }
 impl Point {
-    fn new(x: usize, y: usize, val: usize) -> Self {
-        Self { x, y, val }
-    }
-    // get point left of point
-    fn left(&self, map: &[Vec<usize>]) -> Option<Self> {
-        if self.x > 0 {
-            let x = self.x - 1;
-            let y = self.y;
-            let v = map[x][y];
-            Some(Point::new(x, y, v))
-        } else {
-            None
-        }
-    }
-    // get point right of point
-    fn right(&self, map: &[Vec<usize>]) -> Option<Self> {
-        if self.x < map[0].len() - 1 {
-            let x = self.x + 1;
-            let y = self.y;
-            let v = map[x][y];
-            Some(Point::new(x, y, v))
-        } else {
-            None
-        }
-    }
-    // get point above point
-    fn above(&self, map: &[Vec<usize>]) -> Option<Self> {
-        if self.y > 0 {
-            let x = self.x;
-            let y = self.y - 1;
-            let v = map[x][y];
-            Some(Point::new(x, y, v))
-        } else {
-            None
-        }
-    }
-    // get point below point
-    fn below(&self, map: &[Vec<usize>]) -> Option<Self> {
-        if self.y < map.len() - 1 {
-            let x = self.x;
-            let y = self.y + 1;
-            let v = map[x][y];
-            Some(Point::new(x, y, v))
-        } else {
-            None
-        }
+    fn new(row: usize, col: usize, val: usize) -> Self {
+        Self { row, col, val }
     }
 }
 impl Ord for Point {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.x.cmp(&other.x).then(self.y.cmp(&other.y))
+        self.row.cmp(&other.row).then(self.col.cmp(&other.col))
     }
 }
 impl PartialOrd for Point {
@@ -112,91 +112,269 @@ impl PartialOrd for Point {
 }
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({},{})", self.x, self.y)
+        write!(f, "({},{})", self.row, self.col)
     }
 }
 
-// walk from all the trail heads and score or rate them
-fn assess_trail_heads(trail_heads: Vec<Point>, map: &[Vec<usize>], part2: bool) -> usize {
-    let mut trails = HashMap::new();
-    for point in trail_heads {
-        let mut ways = walk_trail_head(point.clone(), map);
-        if !part2 {
-            ways.sort();
-            ways.dedup();
+// a rectangular topographic map, heights are addressed by row and column
+#[derive(Debug)]
+struct Grid {
+    heights: Vec<Vec<usize>>,
+    rows: usize,
+    cols: usize,
+}
+impl Grid {
+    fn new(heights: Vec<Vec<usize>>) -> Self {
+        let rows = heights.len();
+        let cols = heights.first().map_or(0, |row| row.len());
+        assert!(
+            heights.iter().all(|row| row.len() == cols),
+            "Map isn't rectangular"
+        );
+        Self {
+            heights,
+            rows,
+            cols,
         }
-        trails.insert(point, ways);
     }
-    trails.values().map(|way| way.len()).sum()
+    fn point(&self, row: usize, col: usize) -> Point {
+        Point::new(row, col, self.heights[row][col])
+    }
+    // get all points, row by row
+    fn points(&self) -> Vec<Point> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.cols).map(move |col| (row, col)))
+            .map(|(row, col)| self.point(row, col))
+            .collect()
+    }
+    // get the points left, right, above and below of a point
+    fn neighbours(&self, point: &Point) -> Vec<Point> {
+        let mut neighbours = Vec::new();
+        if point.col > 0 {
+            neighbours.push(self.point(point.row, point.col - 1));
+        }
+        if point.col + 1 < self.cols {
+            neighbours.push(self.point(point.row, point.col + 1));
+        }
+        if point.row > 0 {
+            neighbours.push(self.point(point.row - 1, point.col));
+        }
+        if point.row + 1 < self.rows {
+            neighbours.push(self.point(point.row + 1, point.col));
+        }
+        neighbours
+    }
 }
 
-// walk from a trail head to the top (hopefully!)
-fn walk_trail_head(point: Point, map: &[Vec<usize>]) -> Vec<Point> {
-    let mut tops = Vec::new();
-    let mut steps = vec![point];
-    while let Some(step) = steps.pop() {
-        if step.val != 9 {
-            steps.append(&mut get_steps(&step, map));
-        } else {
-            tops.push(step);
+// rules for walking on a topographic map
+// - trails start at the head height and end at the top height, heights
+//   outside of this range can't be entered
+// - a step can change the height by min_step..=max_step
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    head: usize,
+    top: usize,
+    min_step: i64,
+    max_step: i64,
+}
+impl Rules {
+    fn new(head: usize, top: usize, min_step: i64, max_step: i64) -> Self {
+        assert!(head <= top, "Trail heads need to be below the tops");
+        assert!(min_step <= max_step, "Step range is empty");
+        Self {
+            head,
+            top,
+            min_step,
+            max_step,
         }
     }
-    tops
+    // the puzzle rules: walk from 0 to 9, climbing 1 per step
+    fn puzzle() -> Self {
+        Self::new(0, 9, 1, 1)
+    }
+    // can we step from one point to the other?
+    fn allows(&self, from: &Point, to: &Point) -> bool {
+        let diff = to.val as i64 - from.val as i64;
+        (self.head..=self.top).contains(&to.val) && (self.min_step..=self.max_step).contains(&diff)
+    }
+    // does every step climb? then trails can't walk in circles
+    fn climbing(&self) -> bool {
+        self.min_step > 0
+    }
 }
 
-// find the starting points for a trail
-fn get_trail_heads(map: &[Vec<usize>]) -> Vec<Point> {
-    let mut trail_heads = Vec::new();
-    (0..map.len()).for_each(|x| {
-        (0..map[x].len()).for_each(|y| {
-            if map[x][y] == 0 {
-                trail_heads.push(Point::new(x, y, 0));
-            }
-        });
-    });
+// score or rate the trail heads
+fn assess_trail_heads(trail_heads: Vec<Point>, map: &Grid, rules: &Rules, part2: bool) -> usize {
+    let assessments = score_trail_heads(map, rules);
     trail_heads
+        .iter()
+        .map(|point| {
+            let (score, rating) = assessments.get(point).expect("Trail head not assessed");
+            if part2 {
+                rating.expect("Trails can walk in circles, they can't be rated")
+            } else {
+                *score
+            }
+        })
+        .sum()
 }
 
-// get the points we can walk to from a given point
-fn get_steps(point: &Point, map: &[Vec<usize>]) -> Vec<Point> {
-    let mut ways = Vec::new();
-    let new_val = point.val + 1;
-    if let Some(p) = point.left(map) {
-        if p.val == new_val {
-            ways.push(p);
-        }
+// get the (score, rating) of all trail heads
+// - the rating is only available if every step climbs, otherwise there are
+//   endless trails (find_trails lists the ones without circles)
+fn score_trail_heads(map: &Grid, rules: &Rules) -> HashMap<Point, (usize, Option<usize>)> {
+    if rules.climbing() {
+        score_climbing_trail_heads(map, rules)
+            .into_iter()
+            .map(|(point, (score, rating))| (point, (score, Some(rating))))
+            .collect()
+    } else {
+        get_trail_heads(map, rules)
+            .into_iter()
+            .map(|point| {
+                let score = reachable_tops(&point, map, rules);
+                (point, (score, None))
+            })
+            .collect()
+    }
+}
+
+// get the (score, rating) of all trail heads in one pass over the heights,
+// starting at the tops
+// - the rating of a point is the sum of the ratings of the points it can
+//   step to, a top has a rating of 1
+// - the tops reachable from a point are the union of the tops reachable
+//   from the points it can step to, stored as bit set
+// - only points that reach a top get a bit set, and the bit sets of a height
+//   are dropped as soon as no point left can step to it
+fn score_climbing_trail_heads(map: &Grid, rules: &Rules) -> HashMap<Point, (usize, usize)> {
+    let mut points = map
+        .points()
+        .into_iter()
+        .filter(|p| (rules.head..=rules.top).contains(&p.val))
+        .collect::<Vec<Point>>();
+    points.sort_by_key(|p| Reverse(p.val));
+    let tops = points
+        .iter()
+        .filter(|p| p.val == rules.top)
+        .collect::<Vec<&Point>>();
+    let words = tops.len().div_ceil(64);
+    let mut ratings = vec![vec![0; map.cols]; map.rows];
+    let mut reachable = vec![vec![Vec::new(); map.cols]; map.rows];
+    for (idx, top) in tops.iter().enumerate() {
+        ratings[top.row][top.col] = 1;
+        reachable[top.row][top.col] = vec![0u64; words];
+        reachable[top.row][top.col][idx / 64] |= 1 << (idx % 64);
     }
-    if let Some(p) = point.right(map) {
-        if p.val == new_val {
-            ways.push(p);
+    // the points before this index are too high to be stepped to anymore
+    let mut done = 0;
+    // all steps climb, so the points we can step to are already done
+    for point in points.iter().filter(|p| p.val != rules.top) {
+        while (points[done].val as i64) > point.val as i64 + rules.max_step {
+            reachable[points[done].row][points[done].col] = Vec::new();
+            done += 1;
         }
+        let steps = get_steps(point, map, rules);
+        let rating = steps
+            .iter()
+            .map(|step| ratings[step.row][step.col])
+            .sum::<usize>();
+        ratings[point.row][point.col] = rating;
+        if rating == 0 {
+            continue;
+        }
+        let mut tops = vec![0u64; words];
+        for step in steps {
+            tops.iter_mut()
+                .zip(&reachable[step.row][step.col])
+                .for_each(|(t, r)| *t |= r);
+        }
+        reachable[point.row][point.col] = tops;
     }
-    if let Some(p) = point.above(map) {
-        if p.val == new_val {
-            ways.push(p);
+    points
+        .iter()
+        .filter(|p| p.val == rules.head)
+        .map(|point| {
+            let score = reachable[point.row][point.col]
+                .iter()
+                .map(|t| t.count_ones() as usize)
+                .sum();
+            (point.clone(), (score, ratings[point.row][point.col]))
+        })
+        .collect()
+}
+
+// count the tops reachable from a trail head
+fn reachable_tops(point: &Point, map: &Grid, rules: &Rules) -> usize {
+    let mut visited = HashSet::from([point.clone()]);
+    let mut steps = vec![point.clone()];
+    let mut tops = 0;
+    while let Some(step) = steps.pop() {
+        if step.val == rules.top {
+            tops += 1;
+            continue;
+        }
+        for next in get_steps(&step, map, rules) {
+            if visited.insert(next.clone()) {
+                steps.push(next);
+            }
         }
     }
-    if let Some(p) = point.below(map) {
-        if p.val == new_val {
-            ways.push(p);
+    tops
+}
+
+// get all trails from a trail head to a top, as lists of (row, col)
+// coordinates, a trail never visits a point twice
+// - there can be exponentially many, use score_trail_heads to only count them
+fn find_trails(point: &Point, map: &Grid, rules: &Rules) -> Vec<Vec<(usize, usize)>> {
+    let mut trails = Vec::new();
+    let mut steps = vec![vec![point.clone()]];
+    while let Some(trail) = steps.pop() {
+        let last = trail.last().unwrap();
+        if last.val == rules.top {
+            trails.push(trail.iter().map(|p| (p.row, p.col)).collect());
+            continue;
+        }
+        for step in get_steps(last, map, rules) {
+            if !trail.contains(&step) {
+                let mut next = trail.clone();
+                next.push(step);
+                steps.push(next);
+            }
         }
     }
-    ways
+    trails
+}
+
+// find the starting points for a trail
+fn get_trail_heads(map: &Grid, rules: &Rules) -> Vec<Point> {
+    map.points()
+        .into_iter()
+        .filter(|p| p.val == rules.head)
+        .collect()
+}
+
+// get the points we can walk to from a given point
+fn get_steps(point: &Point, map: &Grid, rules: &Rules) -> Vec<Point> {
+    map.neighbours(point)
+        .into_iter()
+        .filter(|p| rules.allows(point, p))
+        .collect()
 }
 
 // read a topographical map file with height information
-fn read_data(filename: &str) -> Vec<Vec<usize>> {
+fn read_data(filename: &str) -> Grid {
     let mut map = Vec::new();
     if let Ok(lines) = read_lines(filename) {
-        for y in lines.map_while(Result::ok) {
+        for row in lines.map_while(Result::ok) {
             map.push(
-                y.chars()
+                row.chars()
                     .map(|c| c.to_string().parse::<usize>().expect("Couldn't parse char"))
                     .collect(),
             );
         }
     }
-    map
+    Grid::new(map)
 }
 
 // read a file and get the lines
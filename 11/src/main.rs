@@ -11,9 +11,20 @@
 //   - count the number of stones after you blink 25 times
 // - part 2:
 //   - count the number of stones after you blink 75 times
+// - the rules can be replaced by other rule sets, stones and counts are big
+//   integers, so thousands of blinks are possible
+// - the total, distinct, new and largest stones and the amount of stones by
+//   their amount of digits can be exported as CSV for each blink, run with
+//   `stats <blinks> [output file]`, the CSV is printed when no output file is
+//   given
 
-use rayon::prelude::*;
-use std::collections::HashMap;
+use num_bigint::BigUint;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+type Stone = BigUint;
+type Count = BigUint;
+type Matcher = Box<dyn Fn(&Stone) -> bool>;
+type Transformer = Box<dyn Fn(&Stone) -> Vec<Stone>>;
 
 fn main() {
     let input = vec![125, 17];
@@ -32,40 +43,226 @@ fn main() {
     let stones = run(&input, 75);
     assert_eq!(stones, 233007586663131);
     println!("There are {} stones after blinking 75 times", stones);
+
+    let stones = to_stones(&input);
+    let count = count_stones(&stones, &RuleSet::puzzle(), 1000);
+    assert_eq!(count.to_string().len(), 183);
+    assert_eq!(&count % 1_000_000_000_000u64, Count::from(44434003973u64));
+    println!("There are {} stones after blinking 1000 times", count);
+
+    // a variant, where stones with an odd amount of digits split in three
+    let variant = RuleSet::new(vec![
+        Rule::new(|s| *s == Stone::from(0u8), |_| vec![Stone::from(1u8)]),
+        Rule::new(|s| digits(s).is_multiple_of(2), |s| split(s, 2)),
+        Rule::new(|s| digits(s).is_multiple_of(3), |s| split(s, 3)),
+        Rule::new(|_| true, |s| vec![s * 2024u32]),
+    ]);
+    let count = count_stones(&to_stones(&[125, 17]), &variant, 6);
+    assert_eq!(count, Count::from(36u8));
+    let count = count_stones(&to_stones(&[125, 17]), &variant, 25);
+    assert_eq!(count, Count::from(90624u32));
+
+    let stats = analyse(&to_stones(&[125, 17]), &RuleSet::puzzle(), 6);
+    let csv = to_csv(&stats);
+    let lines = csv.lines().collect::<Vec<&str>>();
+    assert_eq!(
+        lines[0],
+        "blink,total,distinct,new,largest,digits_1,digits_2,digits_3,digits_4,\
+         digits_5,digits_6,digits_7,digits_8,digits_9,digits_10"
+    );
+    assert_eq!(lines[1], "0,2,2,2,125,0,1,1,0,0,0,0,0,0,0");
+    assert_eq!(lines[7], "6,22,15,8,2097446912,12,6,0,2,1,0,0,0,0,1");
+
+    let stats = analyse(&to_stones(&input), &RuleSet::puzzle(), 250);
+    assert_eq!(stats[75].total, Count::from(233007586663131u64));
+    let stable = stats.iter().rposition(|s| s.new > 0).unwrap();
+    assert_eq!(stable, 72);
+    println!("No new stone values show up after blink {}", stable);
+
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(|a| a.as_str()) == Some("stats") {
+        let blinks = args
+            .get(2)
+            .map_or(75, |a| a.parse().expect("Invalid amount of blinks"));
+        let csv = to_csv(&analyse(&to_stones(&input), &RuleSet::puzzle(), blinks));
+        match args.get(3) {
+            Some(output) => {
+                std::fs::write(output, csv).expect("Couldn't write statistics");
+                println!("The stone statistics are written to {}", output);
+            }
+            None => print!("{}", csv),
+        }
+    }
+}
+
+// a rule, that transforms a matching stone into one or more stones
+struct Rule {
+    matches: Matcher,
+    transform: Transformer,
+}
+impl Rule {
+    fn new(
+        matches: impl Fn(&Stone) -> bool + 'static,
+        transform: impl Fn(&Stone) -> Vec<Stone> + 'static,
+    ) -> Self {
+        Self {
+            matches: Box::new(matches),
+            transform: Box::new(transform),
+        }
+    }
+}
+
+// an ordered list of rules, the first matching rule is applied to a stone
+// - a stone no rule matches doesn't change
+struct RuleSet {
+    rules: Vec<Rule>,
+}
+impl RuleSet {
+    fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+    // the rules of the puzzle
+    fn puzzle() -> Self {
+        Self::new(vec![
+            Rule::new(|s| *s == Stone::from(0u8), |_| vec![Stone::from(1u8)]),
+            Rule::new(|s| digits(s).is_multiple_of(2), |s| split(s, 2)),
+            Rule::new(|_| true, |s| vec![s * 2024u32]),
+        ])
+    }
+    fn apply(&self, stone: &Stone) -> Vec<Stone> {
+        match self.rules.iter().find(|rule| (rule.matches)(stone)) {
+            Some(rule) => (rule.transform)(stone),
+            None => vec![stone.clone()],
+        }
+    }
+}
+
+// get the amount of digits of a stone
+fn digits(stone: &Stone) -> usize {
+    stone.to_string().len()
+}
+
+// split a stone into parts with the same amount of digits
+fn split(stone: &Stone, parts: usize) -> Vec<Stone> {
+    let digits = stone.to_string();
+    let size = digits.len() / parts;
+    (0..parts)
+        .map(|part| {
+            digits[part * size..(part + 1) * size]
+                .parse()
+                .expect("Couldn't parse stone")
+        })
+        .collect()
+}
+
+fn to_stones(input: &[usize]) -> Vec<Stone> {
+    input.iter().map(|&v| Stone::from(v)).collect()
 }
 
 // blink for a given number of times
 fn run(input: &[usize], runs: u8) -> usize {
-    input
-        .par_iter()
-        .map(|&v| {
-            let mut cache = HashMap::new();
-            blink(v, 0, runs, &mut cache)
-        })
-        .sum()
+    let count = count_stones(&to_stones(input), &RuleSet::puzzle(), runs as usize);
+    usize::try_from(&count).expect("Too many stones")
 }
 
-// recursive blinking
-fn blink(stone: usize, blinks: u8, stop: u8, cache: &mut HashMap<(usize, u8), usize>) -> usize {
-    if blinks >= stop {
-        return 1;
+// count the stones after blinking
+// - the stones are kept as a map of distinct stones and their count, so each
+//   distinct stone is transformed once per blink, no matter how often it
+//   occurs
+// - the transformations are cached, as the same stones come up again in
+//   later blinks
+fn count_stones(input: &[Stone], rules: &RuleSet, blinks: usize) -> Count {
+    let mut stones: HashMap<Stone, Count> = HashMap::new();
+    for stone in input {
+        *stones.entry(stone.clone()).or_default() += 1u8;
     }
-
-    if let Some(&cached_result) = cache.get(&(stone, blinks)) {
-        return cached_result;
+    let mut cache = HashMap::new();
+    for _ in 0..blinks {
+        stones = blink(&stones, rules, &mut cache);
     }
+    stones.values().sum()
+}
+
+// statistics of the stones after a blink
+struct BlinkStats {
+    blink: usize,
+    total: Count,
+    distinct: usize,
+    // stone values that didn't show up in an earlier blink
+    new: usize,
+    largest: Stone,
+    // amount of stones by amount of digits
+    digits: BTreeMap<usize, Count>,
+}
 
-    let result = match stone {
-        0 => blink(1, blinks + 1, stop, cache),
-        num if (&num.ilog10() + 1) % 2 == 0 => {
-            let digits = num.ilog10() + 1;
-            let power = 10_usize.pow(digits / 2);
-            let (l, r) = (num / power, num % power);
-            blink(l, blinks + 1, stop, cache) + blink(r, blinks + 1, stop, cache)
+// collect the stone statistics before the first and after each blink
+fn analyse(input: &[Stone], rules: &RuleSet, blinks: usize) -> Vec<BlinkStats> {
+    let mut stones: HashMap<Stone, Count> = HashMap::new();
+    for stone in input {
+        *stones.entry(stone.clone()).or_default() += 1u8;
+    }
+    let mut cache = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut stats = Vec::new();
+    for blink_nr in 0..=blinks {
+        if blink_nr > 0 {
+            stones = blink(&stones, rules, &mut cache);
+        }
+        let mut digit_counts: BTreeMap<usize, Count> = BTreeMap::new();
+        for (stone, count) in &stones {
+            *digit_counts.entry(digits(stone)).or_default() += count;
         }
-        _ => blink(stone * 2024, blinks + 1, stop, cache),
-    };
+        stats.push(BlinkStats {
+            blink: blink_nr,
+            total: stones.values().sum(),
+            distinct: stones.len(),
+            new: stones.keys().filter(|&s| seen.insert(s.clone())).count(),
+            largest: stones.keys().max().cloned().unwrap_or_default(),
+            digits: digit_counts,
+        });
+    }
+    stats
+}
 
-    cache.insert((stone, blinks), result);
-    result
+// export the statistics as CSV, with one column per amount of digits
+fn to_csv(stats: &[BlinkStats]) -> String {
+    let max_digits = stats
+        .iter()
+        .filter_map(|s| s.digits.keys().last())
+        .max()
+        .copied()
+        .unwrap_or(0);
+    let mut csv = String::from("blink,total,distinct,new,largest");
+    (1..=max_digits).for_each(|d| csv.push_str(&format!(",digits_{}", d)));
+    csv.push('\n');
+    for s in stats {
+        csv.push_str(&format!(
+            "{},{},{},{},{}",
+            s.blink, s.total, s.distinct, s.new, s.largest
+        ));
+        (1..=max_digits).for_each(|d| {
+            let count = s.digits.get(&d).cloned().unwrap_or_default();
+            csv.push_str(&format!(",{}", count));
+        });
+        csv.push('\n');
+    }
+    csv
+}
+
+// blink once
+fn blink(
+    stones: &HashMap<Stone, Count>,
+    rules: &RuleSet,
+    cache: &mut HashMap<Stone, Vec<Stone>>,
+) -> HashMap<Stone, Count> {
+    let mut next: HashMap<Stone, Count> = HashMap::new();
+    for (stone, count) in stones {
+        let new_stones = cache
+            .entry(stone.clone())
+            .or_insert_with(|| rules.apply(stone));
+        for new_stone in new_stones.iter() {
+            *next.entry(new_stone.clone()).or_default() += count;
+        }
+    }
+    next
 }
@@ -10,6 +10,13 @@
 // - part 2:
 //   - the price of the fences is calculated by multiplying area and number of
 //     sides of a region
+// - the regions are labelled by flood filling the map, area, perimeter and
+//   sides of all regions are measured in one sweep over the labels
+// - holes, inner and outer perimeter, bounding box and enclosed regions are
+//   detected for each region, prices can be calculated with other policies
+// - the regions, their sides and corners can be drawn as SVG, run with
+//   `svg <map file> [scale] [output file]`, the SVG is printed when no output
+//   file is given
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -18,181 +25,513 @@ use std::path::Path;
 
 fn main() {
     let map = read_data("input.test");
-    let area = inspect_area(&map);
-    let regions = get_regions(&map, &area);
-    let region_perimeters = get_region_perimeters(&map, &regions);
+    let regions = measure_regions(&label_regions(&map));
+    assert_eq!(regions.len(), 11);
+    let region_perimeters = get_region_perimeters(&regions);
     assert_eq!(get_price(region_perimeters), 1930);
 
     let map = read_data("input");
-    let area = inspect_area(&map);
-    let regions = get_regions(&map, &area);
-    let region_perimeters = get_region_perimeters(&map, &regions);
+    let regions = measure_regions(&label_regions(&map));
+    let region_perimeters = get_region_perimeters(&regions);
     let price = get_price(region_perimeters);
     assert_eq!(price, 1319878);
     println!("The price for all perimeters is {}", price);
 
     let map = read_data("input.test");
-    let area = inspect_area(&map);
-    let regions = get_regions(&map, &area);
-    let region_perimeters = get_region_corners(&map, &regions);
+    let regions = measure_regions(&label_regions(&map));
+    let region_perimeters = get_region_corners(&regions);
     assert_eq!(get_price(region_perimeters), 1206);
 
     let map = read_data("input");
-    let area = inspect_area(&map);
-    let regions = get_regions(&map, &area);
-    let region_perimeters = get_region_corners(&map, &regions);
+    let regions = measure_regions(&label_regions(&map));
+    let region_perimeters = get_region_corners(&regions);
     let price = get_price(region_perimeters);
     assert_eq!(price, 784982);
     println!("The discount price for all perimeters is {}", price);
+
+    let map = read_data("input.test");
+    let regions = measure_regions(&label_regions(&map));
+    assert_eq!(get_policy_price(&regions, &PerimeterPricing), 1930);
+    assert_eq!(get_policy_price(&regions, &SidePricing), 1206);
+    let corners = CornerPricing { fence: 1, post: 2 };
+    assert_eq!(get_policy_price(&regions, &corners), 396);
+    let shared = SharedFencePricing { discount: 50 };
+    assert_eq!(get_policy_price(&regions, &shared), 1169);
+
+    let map = read_data("input2.test");
+    let regions = measure_regions(&label_regions(&map));
+    let o = &regions[0];
+    assert_eq!((o.plant, o.area, o.holes), ('O', 21, 4));
+    assert_eq!(
+        (o.outer_perimeter, o.inner_perimeter, o.sides),
+        (20, 16, 20)
+    );
+    assert_eq!(o.bounding_box, (0, 0, 4, 4));
+    assert_eq!(o.enclosed, vec![1, 2, 3, 4]);
+    assert_eq!(regions[1].bounding_box, (1, 1, 1, 1));
+
+    let map = read_data("input3.test");
+    let regions = measure_regions(&label_regions(&map));
+    let a = &regions[0];
+    assert_eq!((a.plant, a.area, a.holes, a.sides), ('A', 28, 2, 12));
+    assert_eq!((a.outer_perimeter, a.inner_perimeter), (24, 16));
+    assert_eq!(a.enclosed, vec![1, 2]);
+    assert_eq!(get_policy_price(&regions, &SidePricing), 368);
+
+    let map = read_data("input");
+    let regions = measure_regions(&label_regions(&map));
+    assert_eq!(get_policy_price(&regions, &PerimeterPricing), 1319878);
+    assert_eq!(get_policy_price(&regions, &SidePricing), 784982);
+    let holes = regions.iter().map(|r| r.holes).sum::<usize>();
+    assert_eq!(holes, 56);
+    println!("The regions have {} holes", holes);
+
+    for filename in ["input.test", "input2.test", "input3.test", "input"] {
+        let map = read_data(filename);
+        let labels = label_regions(&map);
+        let regions = measure_regions(&labels);
+        let sides = get_sides(&labels);
+        assert!(regions
+            .iter()
+            .all(|r| sides.iter().filter(|s| s.region == r.id).count() == r.sides));
+    }
+    let map = read_data("input3.test");
+    let labels = label_regions(&map);
+    let regions = measure_regions(&labels);
+    let svg = render_svg(&labels, &regions, 20);
+    assert_eq!(svg.matches("<rect").count(), 36);
+    assert_eq!(svg.matches("<line").count(), 20);
+    assert_eq!(svg.matches("<circle").count(), 20);
+
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(|a| a.as_str()) == Some("svg") {
+        let map = read_data(args.get(2).map_or("input", |a| a.as_str()));
+        let scale = args
+            .get(3)
+            .map_or(10, |a| a.parse().expect("Invalid scale"));
+        let labels = label_regions(&map);
+        let regions = measure_regions(&labels);
+        let svg = render_svg(&labels, &regions, scale);
+        match args.get(4) {
+            Some(output) => {
+                std::fs::write(output, svg).expect("Couldn't write SVG");
+                println!("The garden is drawn to {}", output);
+            }
+            None => print!("{}", svg),
+        }
+    }
+}
+
+// the region ID of each position and the plant of each region
+struct Labels {
+    ids: Vec<Vec<usize>>,
+    plants: Vec<char>,
+}
+impl Labels {
+    // get the region ID of a position, if it's on the map
+    fn get(&self, x: i64, y: i64) -> Option<usize> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.ids
+            .get(y as usize)
+            .and_then(|row| row.get(x as usize))
+            .copied()
+    }
 }
 
-// find the different plants and their positions
-fn inspect_area(map: &[Vec<char>]) -> HashMap<char, Vec<(usize, usize)>> {
-    let mut plants: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+// label the regions of the map by flood filling them
+// - every position is visited once when it's labelled and once for each of
+//   its neighbors
+fn label_regions(map: &[Vec<char>]) -> Labels {
+    let mut ids = vec![vec![usize::MAX; map[0].len()]; map.len()];
+    let mut plants = Vec::new();
     (0..map.len()).for_each(|y| {
         (0..map[y].len()).for_each(|x| {
-            plants
-                .entry(map[y][x])
-                .and_modify(|p: &mut Vec<(usize, usize)>| p.push((x, y)))
-                .or_insert(vec![(x, y)]);
-        });
+            if ids[y][x] != usize::MAX {
+                return;
+            }
+            let id = plants.len();
+            let plant = map[y][x];
+            plants.push(plant);
+            ids[y][x] = id;
+            let mut positions = vec![(x, y)];
+            while let Some(position) = positions.pop() {
+                for (nx, ny) in get_neighbors(map, &position) {
+                    if ids[ny][nx] == usize::MAX && map[ny][nx] == plant {
+                        ids[ny][nx] = id;
+                        positions.push((nx, ny));
+                    }
+                }
+            }
+        })
     });
-    plants
+    Labels { ids, plants }
 }
 
-// detect the regions in the map
-fn get_regions(
-    map: &[Vec<char>],
-    area: &HashMap<char, Vec<(usize, usize)>>,
-) -> HashMap<char, Vec<Vec<(usize, usize)>>> {
-    let mut regions: HashMap<char, Vec<Vec<(usize, usize)>>> = HashMap::new();
-    for (plant, positions) in area {
-        for position in positions {
-            // check if the positions is already in a region
-            let mut position_in_regions = false;
-            if let Some(all_regions) = regions.get(plant) {
-                for region in all_regions {
-                    if region.contains(position) {
-                        position_in_regions = true;
-                        break;
-                    }
+// area, perimeter and sides of a region
+#[derive(Debug, Clone, Default)]
+struct Region {
+    id: usize,
+    plant: char,
+    area: usize,
+    perimeter: usize,
+    // fences along the holes and along the rest of the region
+    inner_perimeter: usize,
+    outer_perimeter: usize,
+    // fences on the edge of the map, the others are shared with a neighbor
+    border_perimeter: usize,
+    sides: usize,
+    holes: usize,
+    // (min x, min y, max x, max y)
+    bounding_box: (usize, usize, usize, usize),
+    // IDs of the regions inside the holes
+    enclosed: Vec<usize>,
+}
+impl Region {
+    fn new(id: usize, plant: char) -> Self {
+        Self {
+            id,
+            plant,
+            bounding_box: (usize::MAX, usize::MAX, 0, 0),
+            ..Default::default()
+        }
+    }
+}
+
+// measure all regions in one sweep over the labels, indexed by region ID
+// - each neighbor with another label adds a fence to the perimeter
+// - a region has the same amount of corners as sides, and corner detection
+//   is easier
+fn measure_regions(labels: &Labels) -> Vec<Region> {
+    let mut regions = labels
+        .plants
+        .iter()
+        .enumerate()
+        .map(|(id, &plant)| Region::new(id, plant))
+        .collect::<Vec<Region>>();
+    (0..labels.ids.len()).for_each(|y| {
+        (0..labels.ids[y].len()).for_each(|x| {
+            let id = labels.ids[y][x];
+            let (x, y) = (x as i64, y as i64);
+            let same = |dx: i64, dy: i64| labels.get(x + dx, y + dy) == Some(id);
+            let region = &mut regions[id];
+            region.area += 1;
+            region.perimeter += [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .filter(|(dx, dy)| !same(*dx, *dy))
+                .count();
+            region.border_perimeter += [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                .iter()
+                .filter(|(dx, dy)| labels.get(x + dx, y + dy).is_none())
+                .count();
+            let (min_x, min_y, max_x, max_y) = region.bounding_box;
+            region.bounding_box = (
+                min_x.min(x as usize),
+                min_y.min(y as usize),
+                max_x.max(x as usize),
+                max_y.max(y as usize),
+            );
+            region.sides += [(-1, -1), (1, -1), (-1, 1), (1, 1)]
+                .iter()
+                .filter(|(dx, dy)| {
+                    let (horizontal, vertical) = (same(*dx, 0), same(0, *dy));
+                    // outer corner or inner corner
+                    !(horizontal || vertical) || (horizontal && vertical && !same(*dx, *dy))
+                })
+                .count();
+        })
+    });
+    regions
+        .iter_mut()
+        .for_each(|region| find_holes(labels, region));
+    regions
+}
+
+// find the holes of a region, the regions inside of them and the fences
+// along them
+// - everything that isn't connected to the outside of the bounding box
+//   (without crossing the region) is a hole, positions that only touch
+//   diagonally aren't connected
+fn find_holes(labels: &Labels, region: &mut Region) {
+    let (min_x, min_y, max_x, max_y) = region.bounding_box;
+    // look at the bounding box with a margin of 1, the margin is outside
+    let (x0, y0) = (min_x as i64 - 1, min_y as i64 - 1);
+    let (width, height) = (max_x - min_x + 3, max_y - min_y + 3);
+    let inside = |x: usize, y: usize| labels.get(x0 + x as i64, y0 + y as i64) == Some(region.id);
+    let neighbors = |x: usize, y: usize| {
+        let mut neighbors = Vec::new();
+        if x > 0 {
+            neighbors.push((x - 1, y));
+        }
+        if x + 1 < width {
+            neighbors.push((x + 1, y));
+        }
+        if y > 0 {
+            neighbors.push((x, y - 1));
+        }
+        if y + 1 < height {
+            neighbors.push((x, y + 1));
+        }
+        neighbors
+    };
+    let mut visited = vec![vec![false; width]; height];
+    let fill = |start: (usize, usize), visited: &mut Vec<Vec<bool>>| {
+        let mut positions = vec![start];
+        let mut filled = vec![start];
+        visited[start.1][start.0] = true;
+        while let Some((x, y)) = positions.pop() {
+            for (nx, ny) in neighbors(x, y) {
+                if !visited[ny][nx] && !inside(nx, ny) {
+                    visited[ny][nx] = true;
+                    positions.push((nx, ny));
+                    filled.push((nx, ny));
                 }
-            } else {
-                regions.insert(*plant, Vec::new());
             }
-            if position_in_regions {
-                continue;
-            }
-            // check if a neighbor is in a region and add the position to the
-            // region or create a new region
-            let neighbors = get_neighbors(map, position);
-            let mut neighbour_in_regions = false;
-            for neighbor in neighbors {
-                if let Some(all_regions) = regions.get_mut(plant) {
-                    for region in all_regions {
-                        if region.contains(&neighbor) {
-                            region.push(*position);
-                            neighbour_in_regions = true;
-                            break;
-                        }
-                    }
-                }
-                if neighbour_in_regions {
-                    break;
-                }
+        }
+        filled
+    };
+    fill((0, 0), &mut visited);
+    let mut enclosed = Vec::new();
+    (0..height).for_each(|y| {
+        (0..width).for_each(|x| {
+            if visited[y][x] || inside(x, y) {
+                return;
             }
-            if !neighbour_in_regions {
-                regions
-                    .get_mut(plant)
-                    .expect("No regions")
-                    .push(vec![*position])
+            region.holes += 1;
+            for (hx, hy) in fill((x, y), &mut visited) {
+                enclosed.push(labels.ids[(y0 + hy as i64) as usize][(x0 + hx as i64) as usize]);
+                region.inner_perimeter += neighbors(hx, hy)
+                    .iter()
+                    .filter(|(nx, ny)| inside(*nx, *ny))
+                    .count();
             }
-        }
+        })
+    });
+    enclosed.sort();
+    enclosed.dedup();
+    region.enclosed = enclosed;
+    region.outer_perimeter = region.perimeter - region.inner_perimeter;
+}
 
-        let mut join = join_regions(map, regions.get_mut(plant).expect("Plant not found"));
-        while join {
-            join = join_regions(map, regions.get_mut(plant).expect("Plant not found"));
-        }
-        regions
-            .get_mut(plant)
-            .expect("Plant not found")
-            .iter_mut()
-            .for_each(|v| v.sort());
+// a policy to price the fence of a region
+trait PricingPolicy {
+    fn price(&self, region: &Region) -> usize;
+}
+
+// part 1: area times perimeter
+struct PerimeterPricing;
+impl PricingPolicy for PerimeterPricing {
+    fn price(&self, region: &Region) -> usize {
+        region.area * region.perimeter
     }
-    regions
 }
 
-// join regions in a vector, if they belong together
-fn join_regions(map: &[Vec<char>], regions: &mut Vec<Vec<(usize, usize)>>) -> bool {
-    let mut join = false;
-    for i in 0..regions.len() {
-        for j in 0..regions.len() {
-            if i == j {
-                continue;
+// part 2: area times sides
+struct SidePricing;
+impl PricingPolicy for SidePricing {
+    fn price(&self, region: &Region) -> usize {
+        region.area * region.sides
+    }
+}
+
+// fence material by length and a post at each corner
+struct CornerPricing {
+    fence: usize,
+    post: usize,
+}
+impl PricingPolicy for CornerPricing {
+    fn price(&self, region: &Region) -> usize {
+        self.fence * region.perimeter + self.post * region.sides
+    }
+}
+
+// area times perimeter, with a discount (in percent) on fences shared with
+// a neighboring region
+struct SharedFencePricing {
+    discount: usize,
+}
+impl PricingPolicy for SharedFencePricing {
+    fn price(&self, region: &Region) -> usize {
+        let shared = region.perimeter - region.border_perimeter;
+        region.area * (region.border_perimeter * 100 + shared * (100 - self.discount)) / 100
+    }
+}
+
+// calculate the price of all regions with a pricing policy
+fn get_policy_price(regions: &[Region], policy: &dyn PricingPolicy) -> usize {
+    regions.iter().map(|region| policy.price(region)).sum()
+}
+
+// the direction a side of a region is facing (out of the region)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Facing {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+// a straight side of a region, from start to end in map corner coordinates
+// (the top left corner of position (x, y) is (x, y))
+#[derive(Debug, Clone, PartialEq)]
+struct Side {
+    region: usize,
+    facing: Facing,
+    start: (usize, usize),
+    end: (usize, usize),
+}
+
+// get the sides of all regions by merging the fences along a line, as long
+// as they belong to the same region and face the same direction
+fn get_sides(labels: &Labels) -> Vec<Side> {
+    let height = labels.ids.len();
+    let width = labels.ids[0].len();
+    let mut sides: Vec<Side> = Vec::new();
+    // the index of the side by (region, facing, end), to continue it
+    let mut ends: HashMap<(usize, Facing, (usize, usize)), usize> = HashMap::new();
+    let mut add = |region: usize, facing: Facing, start: (usize, usize), end: (usize, usize)| {
+        let idx = match ends.remove(&(region, facing, start)) {
+            Some(idx) => {
+                sides[idx].end = end;
+                idx
             }
-            for e in &regions[i] {
-                let neighbors = get_neighbors(map, e);
-                for neighbor in neighbors {
-                    if regions[j].contains(&neighbor) {
-                        join = true;
-                        break;
-                    }
-                }
-                if join {
-                    break;
-                }
+            None => {
+                sides.push(Side {
+                    region,
+                    facing,
+                    start,
+                    end,
+                });
+                sides.len() - 1
+            }
+        };
+        ends.insert((region, facing, end), idx);
+    };
+    (0..height).for_each(|y| {
+        (0..width).for_each(|x| {
+            let id = labels.ids[y][x];
+            let (ix, iy) = (x as i64, y as i64);
+            if labels.get(ix, iy - 1) != Some(id) {
+                add(id, Facing::Up, (x, y), (x + 1, y));
+            }
+            if labels.get(ix, iy + 1) != Some(id) {
+                add(id, Facing::Down, (x, y + 1), (x + 1, y + 1));
+            }
+        })
+    });
+    (0..width).for_each(|x| {
+        (0..height).for_each(|y| {
+            let id = labels.ids[y][x];
+            let (ix, iy) = (x as i64, y as i64);
+            if labels.get(ix - 1, iy) != Some(id) {
+                add(id, Facing::Left, (x, y), (x, y + 1));
             }
-            if join {
-                let mut t = regions.remove(j);
-                regions[i].append(&mut t);
-                break;
+            if labels.get(ix + 1, iy) != Some(id) {
+                add(id, Facing::Right, (x + 1, y), (x + 1, y + 1));
             }
+        })
+    });
+    sides
+}
+
+// render the garden as SVG, to check the side counting
+// - each region is filled with its own color and has a tooltip with plant,
+//   area and sides
+// - each side is drawn as a line, moved a bit into its region, with corner
+//   markers at both ends
+fn render_svg(labels: &Labels, regions: &[Region], scale: usize) -> String {
+    let height = labels.ids.len();
+    let width = labels.ids[0].len();
+    let color = |id: usize| format!("hsl({:.1},65%,65%)", (id as f64 * 137.508) % 360.0);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+        width * scale,
+        height * scale
+    );
+    let mut cells: Vec<Vec<(usize, usize)>> = vec![Vec::new(); regions.len()];
+    (0..height).for_each(|y| (0..width).for_each(|x| cells[labels.ids[y][x]].push((x, y))));
+    for region in regions {
+        svg.push_str(&format!(
+            "<g fill=\"{}\"><title>{}: area {}, sides {}</title>\n",
+            color(region.id),
+            region.plant,
+            region.area,
+            region.sides
+        ));
+        for (x, y) in &cells[region.id] {
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                x * scale,
+                y * scale,
+                scale,
+                scale
+            ));
         }
-        if join {
-            break;
+        svg.push_str("</g>\n");
+    }
+    let inset = scale as f64 / 8.0;
+    for side in get_sides(labels) {
+        let (dx, dy) = match side.facing {
+            Facing::Up => (0.0, inset),
+            Facing::Down => (0.0, -inset),
+            Facing::Left => (inset, 0.0),
+            Facing::Right => (-inset, 0.0),
+        };
+        let start = (
+            (side.start.0 * scale) as f64 + dx,
+            (side.start.1 * scale) as f64 + dy,
+        );
+        let end = (
+            (side.end.0 * scale) as f64 + dx,
+            (side.end.1 * scale) as f64 + dy,
+        );
+        svg.push_str(&format!(
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\" stroke-width=\"{}\"/>\n",
+            start.0,
+            start.1,
+            end.0,
+            end.1,
+            inset / 2.0
+        ));
+        // every corner is the end of exactly one horizontal side
+        if matches!(side.facing, Facing::Up | Facing::Down) {
+            for (x, y) in [start, end] {
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>\n",
+                    x, y, inset
+                ));
+            }
         }
     }
-    join
+    svg.push_str("</svg>\n");
+    svg
 }
 
-// get the perimeter details
-fn get_region_perimeters(
-    map: &[Vec<char>],
-    regions: &HashMap<char, Vec<Vec<(usize, usize)>>>,
-) -> HashMap<char, Vec<(usize, usize)>> {
+// get the (size, perimeter) of the regions of each plant
+fn get_region_perimeters(regions: &[Region]) -> HashMap<char, Vec<(usize, usize)>> {
     let mut perimeters: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    for plant in regions.keys() {
-        if let Some(all_regions) = regions.get(plant) {
-            for region in all_regions {
-                let mut region_perimeter = 0;
-                let mut region_size = 0;
-                for position in region {
-                    let neighbors = get_neighbors(map, position);
-                    let mut perimeter = match neighbors.len() {
-                        3 => 1,
-                        2 => 2,
-                        _ => 0,
-                    };
-                    for neighbor in neighbors {
-                        if map[neighbor.1][neighbor.0] != *plant {
-                            perimeter += 1;
-                        }
-                    }
-                    region_size += 1;
-                    region_perimeter += perimeter;
-                }
-                perimeters
-                    .entry(*plant)
-                    .and_modify(|p: &mut Vec<(usize, usize)>| {
-                        p.push((region_size, region_perimeter))
-                    })
-                    .or_insert(vec![(region_size, region_perimeter)]);
-            }
-        }
+    for region in regions {
+        perimeters
+            .entry(region.plant)
+            .or_default()
+            .push((region.area, region.perimeter));
     }
     perimeters
 }
 
+// get the (size, corners) of the regions of each plant
+fn get_region_corners(regions: &[Region]) -> HashMap<char, Vec<(usize, usize)>> {
+    let mut corners: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
+    for region in regions {
+        corners
+            .entry(region.plant)
+            .or_default()
+            .push((region.area, region.sides));
+    }
+    corners
+}
+
 // get the neighboring positions of a position
 fn get_neighbors(map: &[Vec<char>], position: &(usize, usize)) -> Vec<(usize, usize)> {
     let mut neighbors = Vec::new();
@@ -226,123 +565,6 @@ fn get_price(region_perimeters: HashMap<char, Vec<(usize, usize)>>) -> usize {
     sum
 }
 
-// get the corners of each region
-// a region has the same amount of corners as edges
-// and corner detection is easier
-fn get_region_corners(
-    map: &[Vec<char>],
-    regions: &HashMap<char, Vec<Vec<(usize, usize)>>>,
-) -> HashMap<char, Vec<(usize, usize)>> {
-    let mut corners: HashMap<char, Vec<(usize, usize)>> = HashMap::new();
-    for plant in regions.keys() {
-        if let Some(all_regions) = regions.get(plant) {
-            for region in all_regions {
-                let mut region_edges = 0;
-                let mut region_size = 0;
-                for position in region {
-                    region_size += 1;
-                    region_edges += get_corners(map, position);
-                }
-                corners
-                    .entry(*plant)
-                    .and_modify(|p: &mut Vec<(usize, usize)>| p.push((region_size, region_edges)))
-                    .or_insert(vec![(region_size, region_edges)]);
-            }
-        }
-    }
-    corners
-}
-
-// get the amount of corners of a position
-// check all 8 surrounding positions and detect if it's a corner
-// return the amount of corners on this position
-fn get_corners(map: &[Vec<char>], position: &(usize, usize)) -> usize {
-    let n = if position.1 > 0 {
-        is_same(position.0, position.1 - 1, map[position.1][position.0], map)
-    } else {
-        false
-    };
-    let ne = if position.1 > 0 {
-        is_same(
-            position.0 + 1,
-            position.1 - 1,
-            map[position.1][position.0],
-            map,
-        )
-    } else {
-        false
-    };
-    let e = is_same(position.0 + 1, position.1, map[position.1][position.0], map);
-    let se = is_same(
-        position.0 + 1,
-        position.1 + 1,
-        map[position.1][position.0],
-        map,
-    );
-    let s = is_same(position.0, position.1 + 1, map[position.1][position.0], map);
-    let sw = if position.0 > 0 {
-        is_same(
-            position.0 - 1,
-            position.1 + 1,
-            map[position.1][position.0],
-            map,
-        )
-    } else {
-        false
-    };
-    let w = if position.0 > 0 {
-        is_same(position.0 - 1, position.1, map[position.1][position.0], map)
-    } else {
-        false
-    };
-    let nw = if position.0 > 0 && position.1 > 0 {
-        is_same(
-            position.0 - 1,
-            position.1 - 1,
-            map[position.1][position.0],
-            map,
-        )
-    } else {
-        false
-    };
-
-    let mut corners = 0;
-    if n && w && !nw {
-        corners += 1;
-    }
-    if n && e && !ne {
-        corners += 1;
-    }
-    if s && w && !sw {
-        corners += 1;
-    }
-    if s && e && !se {
-        corners += 1;
-    }
-    if !(n || w) {
-        corners += 1;
-    }
-    if !(n || e) {
-        corners += 1;
-    }
-    if !(s || w) {
-        corners += 1;
-    }
-    if !(s || e) {
-        corners += 1;
-    }
-
-    corners
-}
-
-// check if a position has the same plant and is in range
-// because of usize we can't do x,y < 0 here
-fn is_same(x: usize, y: usize, plant: char, map: &[Vec<char>]) -> bool {
-    let xr = 0..map[0].len();
-    let yr = 0..map.len();
-    xr.contains(&x) && yr.contains(&y) && map[y][x] == plant
-}
-
 // read a garden map file with plant information
 fn read_data(filename: &str) -> Vec<Vec<char>> {
     let mut map = Vec::new();
@@ -6,6 +6,12 @@
 // - pressing button A costs 3 token
 // - pressing button B costs 1 token
 // - no button is pressed more than 100x
+// - the token costs and the press limit can be configured, machines with
+//   collinear buttons are solved by finding the cheapest of all solutions
+// - machines can have any amount of buttons and dimensions, they are solved
+//   as integer linear programs
+// - the parser accepts CRLF line endings, extra blank lines and negative
+//   offsets and reports the block and field of missing or malformed data
 // - part 1:
 //   - calculate the fewest amount of tokens to win all possible prizes
 // - part 2:
@@ -26,6 +32,15 @@ fn main() {
     let price: i64 = machines.iter_mut().map(|m| get_price(m, true)).sum();
     assert_eq!(price, 96979582619758);
     println!("{} coins need to be spent", price);
+
+    let machines = read_general_data("input");
+    let price = machines
+        .iter()
+        .map(|m| get_general_tokens(m, Some(100)))
+        .sum::<Result<i64, String>>()
+        .expect("Machine can't be solved");
+    assert_eq!(price, 28887);
+    println!("{} coins need to be spent with the general solver", price);
 }
 
 #[derive(Debug)]
@@ -40,64 +55,790 @@ impl Machine {
     }
 }
 
-// get the price to reach the prize by solving
-// a_x * a + b_x * b = p_x
-// a_y * a + b_y * b = p_y
+// token costs of the buttons and the maximum amount of presses per button
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    cost_a: i64,
+    cost_b: i64,
+    limit: Option<i64>,
+}
+impl Config {
+    fn new(cost_a: i64, cost_b: i64, limit: Option<i64>) -> Self {
+        Self {
+            cost_a,
+            cost_b,
+            limit,
+        }
+    }
+}
+
+// get the price to reach the prize, 0 if it can't be reached
 fn get_price(m: &mut Machine, part2: bool) -> i64 {
     if part2 {
         m.prize.0 += 10000000000000;
         m.prize.1 += 10000000000000;
     }
-    let b = (m.prize.1 * m.a.0 - m.prize.0 * m.a.1) / (m.b.1 * m.a.0 - m.b.0 * m.a.1);
-    let a = (m.prize.0 - b * m.b.0) / m.a.0;
-    let x = m.a.0 * a + m.b.0 * b;
-    let y = m.a.1 * a + m.b.1 * b;
-    if (x, y) != m.prize {
-        return 0;
+    let config = if part2 {
+        Config::new(3, 1, None)
+    } else {
+        Config::new(3, 1, Some(100))
+    };
+    get_tokens(m, &config)
+}
+
+// get the tokens to reach the prize with a configuration, 0 if it can't be
+// reached
+fn get_tokens(m: &Machine, config: &Config) -> i64 {
+    match solve(m, config) {
+        Some((a, b)) => a * config.cost_a + b * config.cost_b,
+        None => 0,
+    }
+}
+
+// get the cheapest button presses (a, b) to reach the prize by solving
+// a_x * a + b_x * b = p_x
+// a_y * a + b_y * b = p_y
+// - if the buttons aren't collinear, there's only one solution (Cramer's rule)
+// - otherwise both equations describe the same line, and the cheapest
+//   solution of one of them is searched (extended Euclid)
+// - the token costs mustn't be negative
+fn solve(m: &Machine, config: &Config) -> Option<(i64, i64)> {
+    let (ax, ay) = (m.a.0 as i128, m.a.1 as i128);
+    let (bx, by) = (m.b.0 as i128, m.b.1 as i128);
+    let (px, py) = (m.prize.0 as i128, m.prize.1 as i128);
+    let det = ax * by - bx * ay;
+    let (a, b) = if det != 0 {
+        let a = px * by - bx * py;
+        let b = ax * py - px * ay;
+        if a % det != 0 || b % det != 0 {
+            return None;
+        }
+        (a / det, b / det)
+    } else if ax != 0 || bx != 0 {
+        solve_line(ax, bx, px, config)?
+    } else {
+        solve_line(ay, by, py, config)?
+    };
+    let limit = config.limit.map_or(i128::MAX, |l| l as i128);
+    if !(0..=limit).contains(&a) || !(0..=limit).contains(&b) {
+        return None;
+    }
+    // the other equation might not be on the same line
+    if (ax * a + bx * b, ay * a + by * b) != (px, py) {
+        return None;
     }
-    a * 3 + b
+    Some((a as i64, b as i64))
+}
+
+// get the cheapest presses (a, b) for u * a + v * b = w
+// - all solutions are a = a0 + k * v / g and b = b0 - k * u / g
+// - the cost is linear in k, so the cheapest solution is at one end of the
+//   range of k, where a and b are within 0 and the limit
+fn solve_line(u: i128, v: i128, w: i128, config: &Config) -> Option<(i128, i128)> {
+    let limit = config.limit.map(|l| l as i128);
+    if u == 0 || v == 0 {
+        // one button doesn't move, so it's never pressed
+        let (d, other) = if u == 0 { (v, (0, 1)) } else { (u, (1, 0)) };
+        if d == 0 || w % d != 0 {
+            return if w == 0 { Some((0, 0)) } else { None };
+        }
+        return Some((other.0 * w / d, other.1 * w / d));
+    }
+    let (g, x, y) = extended_gcd(u, v);
+    if w % g != 0 {
+        return None;
+    }
+    let (a0, b0) = (x * (w / g), y * (w / g));
+    let (da, db) = (v / g, -u / g);
+    let (lo_a, hi_a) = k_range(a0, da, limit);
+    let (lo_b, hi_b) = k_range(b0, db, limit);
+    let lo = lo_a.max(lo_b);
+    let hi = hi_a.min(hi_b);
+    if lo > hi {
+        return None;
+    }
+    let slope = config.cost_a as i128 * da + config.cost_b as i128 * db;
+    // with non-negative costs, the cheaper end is always bounded
+    let k = if slope > 0 || hi == i128::MAX { lo } else { hi };
+    Some((a0 + k * da, b0 + k * db))
+}
+
+// get the range of k, where 0 <= start + k * step <= limit
+fn k_range(start: i128, step: i128, limit: Option<i128>) -> (i128, i128) {
+    let (mut lo, mut hi) = (i128::MIN, i128::MAX);
+    if step > 0 {
+        lo = div_ceil(-start, step);
+        if let Some(limit) = limit {
+            hi = div_floor(limit - start, step);
+        }
+    } else {
+        hi = div_floor(start, -step);
+        if let Some(limit) = limit {
+            lo = div_ceil(start - limit, -step);
+        }
+    }
+    (lo, hi)
+}
+
+fn div_floor(a: i128, b: i128) -> i128 {
+    a.div_euclid(b)
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -(-a).div_euclid(b)
+}
+
+// get (g, x, y) with u * x + v * y = g = gcd(u, v)
+fn extended_gcd(u: i128, v: i128) -> (i128, i128, i128) {
+    if v == 0 {
+        (u, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(v, u % v);
+        (g, y, x - (u / v) * y)
+    }
+}
+
+// a button with its movement in each dimension and its token cost
+#[derive(Debug, Clone)]
+struct Button {
+    name: char,
+    moves: Vec<i64>,
+    cost: i64,
+}
+
+// a claw machine with any amount of buttons and dimensions
+#[derive(Debug, Clone)]
+struct GeneralMachine {
+    axes: Vec<char>,
+    buttons: Vec<Button>,
+    prize: Vec<i64>,
+}
+impl GeneralMachine {
+    // get the two button machine, for machines with buttons A and B and
+    // prizes in X and Y
+    fn to_machine(&self) -> Result<Machine, String> {
+        let mut axes = self.axes.clone();
+        axes.sort();
+        if axes != vec!['X', 'Y'] {
+            return Err("Prize needs to be in X and Y".to_string());
+        }
+        let mut names = self.buttons.iter().map(|b| b.name).collect::<Vec<char>>();
+        names.sort();
+        if names != vec!['A', 'B'] {
+            return Err("Machine needs buttons A and B".to_string());
+        }
+        let (x, y) = (self.axis('X'), self.axis('Y'));
+        let a = &self.button('A').moves;
+        let b = &self.button('B').moves;
+        Ok(Machine::new(
+            (a[x], a[y]),
+            (b[x], b[y]),
+            (self.prize[x], self.prize[y]),
+        ))
+    }
+    fn axis(&self, letter: char) -> usize {
+        self.axes.iter().position(|&a| a == letter).unwrap()
+    }
+    fn button(&self, name: char) -> &Button {
+        self.buttons.iter().find(|b| b.name == name).unwrap()
+    }
+}
+
+// get the tokens to reach the prize of a general machine, 0 if it can't be
+// reached, an error if the numbers get too large
+fn get_general_tokens(m: &GeneralMachine, limit: Option<i64>) -> Result<i64, String> {
+    Ok(match solve_general(m, limit)? {
+        Some(presses) => presses
+            .iter()
+            .zip(&m.buttons)
+            .map(|(p, b)| p * b.cost)
+            .sum(),
+        None => 0,
+    })
+}
+
+// get the cheapest button presses to reach the prize of a general machine,
+// an error if the numbers get too large
+fn solve_general(m: &GeneralMachine, limit: Option<i64>) -> Result<Option<Vec<i64>>, String> {
+    let columns = m
+        .buttons
+        .iter()
+        .map(|b| b.moves.iter().map(|&v| v as i128).collect())
+        .collect::<Vec<Vec<i128>>>();
+    let prize = m.prize.iter().map(|&v| v as i128).collect::<Vec<i128>>();
+    let costs = m
+        .buttons
+        .iter()
+        .map(|b| b.cost as i128)
+        .collect::<Vec<i128>>();
+    let Some(presses) = solve_system(&columns, &prize, &costs, limit.map(|l| l as i128))? else {
+        return Ok(None);
+    };
+    presses
+        .iter()
+        .map(|&p| i64::try_from(p).map_err(|_| OVERFLOW.to_string()))
+        .collect::<Result<Vec<i64>, String>>()
+        .map(Some)
+}
+
+const OVERFLOW: &str = "The numbers are too large to solve the machine";
+
+// solve the integer linear program: minimize costs * x with
+// columns * x = prize and 0 <= x <= limit, the costs can't be negative
+// - presses that get the claw back to where it started (a loop) are never
+//   needed in full, so one of the buttons of a loop is pressed less often
+//   than in the loop, each choice is solved without that button
+// - without loops, the columns are brought to echelon form with unimodular
+//   column operations (Hermite normal form), giving one integer solution x0
+//   and a basis of the integer solutions of columns * x = 0
+// - all solutions are x0 plus a combination of the basis, the basis is
+//   changed so that only its first vector changes the cost
+// - the basis and x0 are kept short, the echelon form makes their numbers
+//   grow quickly with more buttons
+// - the cheapest solution is the first one found, going along the first
+//   basis vector
+// - all arithmetic is checked, numbers that don't fit are an error
+fn solve_system(
+    columns: &[Vec<i128>],
+    prize: &[i128],
+    costs: &[i128],
+    limit: Option<i128>,
+) -> Result<Option<Vec<i128>>, String> {
+    if let Some(cycle) = find_loop(columns)? {
+        let mut best: Option<(i128, Vec<i128>)> = None;
+        for (button, &times) in cycle.iter().enumerate().filter(|(_, t)| **t > 0) {
+            let mut columns = columns.to_vec();
+            let column = columns.remove(button);
+            let mut rest_costs = costs.to_vec();
+            rest_costs.remove(button);
+            for presses in 0..limit.map_or(times, |l| times.min(l + 1)) {
+                let rest = combine(prize, 1, &column, -presses)?;
+                if let Some(mut x) = solve_system(&columns, &rest, &rest_costs, limit)? {
+                    x.insert(button, presses);
+                    let cost = dot(costs, &x)?;
+                    if best.as_ref().is_none_or(|(b, _)| cost < *b) {
+                        best = Some((cost, x));
+                    }
+                }
+            }
+        }
+        return Ok(best.map(|(_, x)| x));
+    }
+    let Some((x0, kernel)) = integer_solutions(columns, prize)? else {
+        return Ok(None);
+    };
+    let kernel = reduce_basis(&kernel, 0)?;
+    let x0 = reduce_point(&x0, &kernel)?;
+    let kernel = reduce_basis(&cost_basis(&kernel, costs)?, 1)?;
+    first_solution(&x0, &kernel, limit)
+}
+
+// get the presses of a loop, presses that add up to no movement at all, the
+// loop uses as few buttons as possible
+// - a loop of k buttons is the only solution (up to a factor) of
+//   columns * x = 0 for these buttons, with all presses positive
+fn find_loop(columns: &[Vec<i128>]) -> Result<Option<Vec<i128>>, String> {
+    let n = columns.len();
+    let rows = columns.first().map_or(0, |c| c.len());
+    for size in 1..=n.min(rows + 1) {
+        for buttons in combinations(n, size) {
+            let selected = buttons
+                .iter()
+                .map(|&j| columns[j].clone())
+                .collect::<Vec<Vec<i128>>>();
+            let Some((_, kernel)) = integer_solutions(&selected, &vec![0; rows])? else {
+                continue;
+            };
+            if kernel.len() == 1
+                && (kernel[0].iter().all(|&k| k > 0) || kernel[0].iter().all(|&k| k < 0))
+            {
+                let mut cycle = vec![0; n];
+                buttons
+                    .iter()
+                    .zip(&kernel[0])
+                    .for_each(|(&j, k)| cycle[j] = k.abs());
+                return Ok(Some(cycle));
+            }
+        }
+    }
+    Ok(None)
+}
+
+// change the basis with unimodular operations, so that only the first vector
+// changes the cost and it doesn't make it cheaper
+fn cost_basis(kernel: &[Vec<i128>], costs: &[i128]) -> Result<Vec<Vec<i128>>, String> {
+    let mut kernel = kernel.to_vec();
+    for i in 1..kernel.len() {
+        let (u, v) = (dot(costs, &kernel[0])?, dot(costs, &kernel[i])?);
+        if v == 0 {
+            continue;
+        }
+        let (g, s, t) = extended_gcd(u, v);
+        let (first, other) = (kernel[0].clone(), kernel[i].clone());
+        kernel[0] = combine(&first, s, &other, t)?;
+        kernel[i] = combine(&first, v / g, &other, -u / g)?;
+    }
+    if let Some(first) = kernel.first_mut() {
+        if dot(costs, first)? < 0 {
+            first.iter_mut().for_each(|x| *x = -*x);
+        }
+    }
+    Ok(kernel)
+}
+
+// shorten the basis vectors with unimodular operations
+// - the vectors from `fixed` on are reduced against each other, until no
+//   vector gets shorter by subtracting a multiple of another one
+// - the vectors before are only reduced against them, this keeps the cost of
+//   the first vector, when the others don't change the cost
+fn reduce_basis(kernel: &[Vec<i128>], fixed: usize) -> Result<Vec<Vec<i128>>, String> {
+    let mut kernel = kernel.to_vec();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for i in 0..kernel.len() {
+            for j in fixed..kernel.len() {
+                if i != j && shorten(&mut kernel, i, j)? {
+                    changed = true;
+                }
+            }
+        }
+    }
+    Ok(kernel)
+}
+
+// subtract the multiple of vector j from vector i, that makes it shortest,
+// true if vector i got shorter
+fn shorten(kernel: &mut [Vec<i128>], i: usize, j: usize) -> Result<bool, String> {
+    let q = shortening_multiple(&kernel[i], &kernel[j])?;
+    if q != 0 {
+        kernel[i] = combine(&kernel[i], 1, &kernel[j], -q)?;
+    }
+    Ok(q != 0)
+}
+
+// move x0 along the basis vectors, as close to 0 as they get it, so the
+// numbers of the corners stay small
+fn reduce_point(x0: &[i128], kernel: &[Vec<i128>]) -> Result<Vec<i128>, String> {
+    let mut x0 = x0.to_vec();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for step in kernel {
+            let q = shortening_multiple(&x0, step)?;
+            if q != 0 {
+                x0 = combine(&x0, 1, step, -q)?;
+                changed = true;
+            }
+        }
+    }
+    Ok(x0)
+}
+
+// get the integer q, where x - q * step is shortest, 0 if no q makes x
+// shorter
+// - with t = x * step / step * step, x gets shorter when (q - t)² < t²,
+//   that's when q * (q * step * step - 2 * x * step) < 0, so the length of x
+//   itself is never needed
+fn shortening_multiple(x: &[i128], step: &[i128]) -> Result<i128, String> {
+    let length = dot(step, step)?;
+    if length == 0 {
+        return Ok(0);
+    }
+    let twice = dot(x, step)?.checked_mul(2).ok_or(OVERFLOW)?;
+    let q = div_floor(twice + length, 2 * length);
+    let change = q.checked_mul(length).ok_or(OVERFLOW)? - twice;
+    Ok(if q.signum() * change.signum() < 0 {
+        q
+    } else {
+        0
+    })
+}
+
+// s * a + t * b, checked for overflows
+fn combine(a: &[i128], s: i128, b: &[i128], t: i128) -> Result<Vec<i128>, String> {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| {
+            s.checked_mul(*a)
+                .zip(t.checked_mul(*b))
+                .and_then(|(a, b)| a.checked_add(b))
+                .ok_or(OVERFLOW.to_string())
+        })
+        .collect()
+}
+
+// the dot product, checked for overflows
+fn dot(a: &[i128], b: &[i128]) -> Result<i128, String> {
+    a.iter().zip(b).try_fold(0i128, |sum, (a, b)| {
+        a.checked_mul(*b)
+            .and_then(|p| sum.checked_add(p))
+            .ok_or(OVERFLOW.to_string())
+    })
+}
+
+// get the solution x0 + kernel * z with 0 <= x <= limit, with the smallest
+// z[0], then the smallest z[1] and so on
+// - the range of z[0] is given by the corners of the valid region
+fn first_solution(
+    x0: &[i128],
+    kernel: &[Vec<i128>],
+    limit: Option<i128>,
+) -> Result<Option<Vec<i128>>, String> {
+    let Some(step) = kernel.first() else {
+        return Ok(x0
+            .iter()
+            .all(|&x| x >= 0 && limit.is_none_or(|l| x <= l))
+            .then(|| x0.to_vec()));
+    };
+    let Some((lo, hi)) = coordinate_range(x0, kernel, limit)? else {
+        return Ok(None);
+    };
+    for z in lo..=hi {
+        let x = combine(x0, 1, step, z)?;
+        if let Some(solution) = first_solution(&x, &kernel[1..], limit)? {
+            return Ok(Some(solution));
+        }
+    }
+    Ok(None)
+}
+
+// get the range of the integer values of z[0], where x0 + kernel * z with
+// 0 <= x <= limit has a solution, None if there's none
+// - the range is given by the corners of the region, where as many bounds
+//   as there are basis vectors are met exactly, solved with Cramer's rule
+// - the region has to be bounded, which it is without loops
+fn coordinate_range(
+    x0: &[i128],
+    kernel: &[Vec<i128>],
+    limit: Option<i128>,
+) -> Result<Option<(i128, i128)>, String> {
+    let dims = kernel.len();
+    let bounds = (0..x0.len())
+        .flat_map(|j| [Some((j, 0)), limit.map(|l| (j, l))])
+        .flatten()
+        .collect::<Vec<(usize, i128)>>();
+    let mut range: Option<(i128, i128)> = None;
+    for corner in combinations(bounds.len(), dims) {
+        let matrix = corner
+            .iter()
+            .map(|&b| kernel.iter().map(|k| k[bounds[b].0]).collect())
+            .collect::<Vec<Vec<i128>>>();
+        let det = determinant(&matrix)?;
+        if det == 0 {
+            continue;
+        }
+        // z = numerators / den
+        let den = det.abs();
+        let numerators = (0..dims)
+            .map(|i| {
+                let mut replaced = matrix.clone();
+                for (row, &b) in replaced.iter_mut().zip(&corner) {
+                    row[i] = bounds[b].1.checked_sub(x0[bounds[b].0]).ok_or(OVERFLOW)?;
+                }
+                Ok(determinant(&replaced)? * det.signum())
+            })
+            .collect::<Result<Vec<i128>, String>>()?;
+        let mut inside = true;
+        for (j, x) in x0.iter().enumerate() {
+            let column = kernel.iter().map(|k| k[j]).collect::<Vec<i128>>();
+            let x = x
+                .checked_mul(den)
+                .and_then(|x| x.checked_add(dot(&column, &numerators).ok()?))
+                .ok_or(OVERFLOW)?;
+            let upper = limit
+                .map(|l| l.checked_mul(den).ok_or(OVERFLOW))
+                .transpose()?;
+            inside &= x >= 0 && upper.is_none_or(|u| x <= u);
+        }
+        if inside {
+            let (lo, hi) = (div_ceil(numerators[0], den), div_floor(numerators[0], den));
+            range = Some(range.map_or((lo, hi), |(l, h)| (l.min(lo), h.max(hi))));
+        }
+    }
+    Ok(range.filter(|(lo, hi)| lo <= hi))
+}
+
+// all ways to choose k of the indices 0..n, in increasing order
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+    (k - 1..n)
+        .flat_map(|last| {
+            combinations(last, k - 1).into_iter().map(move |mut c| {
+                c.push(last);
+                c
+            })
+        })
+        .collect()
+}
+
+// determinant of a square matrix, fraction free elimination (Bareiss) keeps
+// all intermediate values integer, an error if they don't fit
+fn determinant(matrix: &[Vec<i128>]) -> Result<i128, String> {
+    let n = matrix.len();
+    let mut m = matrix.to_vec();
+    let (mut sign, mut previous) = (1, 1);
+    for k in 0..n {
+        if m[k][k] == 0 {
+            match (k + 1..n).find(|&i| m[i][k] != 0) {
+                Some(i) => {
+                    m.swap(k, i);
+                    sign = -sign;
+                }
+                None => return Ok(0),
+            }
+        }
+        for i in k + 1..n {
+            for j in k + 1..n {
+                m[i][j] = m[i][j]
+                    .checked_mul(m[k][k])
+                    .zip(m[i][k].checked_mul(m[k][j]))
+                    .and_then(|(a, b)| a.checked_sub(b))
+                    .ok_or(OVERFLOW)?
+                    / previous;
+            }
+        }
+        previous = m[k][k];
+    }
+    Ok(match n {
+        0 => 1,
+        _ => sign * m[n - 1][n - 1],
+    })
+}
+
+// one integer solution and a basis of the solutions of the homogeneous system
+type Solutions = (Vec<i128>, Vec<Vec<i128>>);
+
+// get one integer solution x0 of columns * x = prize and a basis of the
+// integer solutions of columns * x = 0
+fn integer_solutions(columns: &[Vec<i128>], prize: &[i128]) -> Result<Option<Solutions>, String> {
+    let n = columns.len();
+    let mut matrix = columns.to_vec();
+    // transform[j] is the combination of the original columns in column j
+    let mut transform = (0..n)
+        .map(|j| (0..n).map(|i| (i == j) as i128).collect())
+        .collect::<Vec<Vec<i128>>>();
+    let mut pivots = Vec::new();
+    for row in 0..prize.len() {
+        let pivot = pivots.len();
+        if pivot == n {
+            break;
+        }
+        for col in pivot + 1..n {
+            // Euclid on the columns, until the entry in col is 0
+            while matrix[col][row] != 0 {
+                let q = matrix[pivot][row] / matrix[col][row];
+                column_operation(&mut matrix, pivot, col, q)?;
+                column_operation(&mut transform, pivot, col, q)?;
+            }
+        }
+        if matrix[pivot][row] != 0 {
+            pivots.push(row);
+        }
+    }
+    // forward substitution in the pivot rows
+    let mut y = Vec::new();
+    for (k, &row) in pivots.iter().enumerate() {
+        let entries = (0..k).map(|j| matrix[j][row]).collect::<Vec<i128>>();
+        let rest = prize[row].checked_sub(dot(&entries, &y)?).ok_or(OVERFLOW)?;
+        if rest % matrix[k][row] != 0 {
+            return Ok(None);
+        }
+        y.push(rest / matrix[k][row]);
+    }
+    let x0 = (0..n)
+        .map(|i| {
+            let entries = (0..y.len()).map(|k| transform[k][i]).collect::<Vec<i128>>();
+            dot(&entries, &y)
+        })
+        .collect::<Result<Vec<i128>, String>>()?;
+    // the non pivot rows might not be met
+    for (row, p) in prize.iter().enumerate() {
+        let entries = columns.iter().map(|c| c[row]).collect::<Vec<i128>>();
+        if dot(&entries, &x0)? != *p {
+            return Ok(None);
+        }
+    }
+    Ok(Some((x0, transform[pivots.len()..].to_vec())))
+}
+
+// subtract q times column col from column pivot and swap them
+fn column_operation(
+    matrix: &mut [Vec<i128>],
+    pivot: usize,
+    col: usize,
+    q: i128,
+) -> Result<(), String> {
+    matrix[pivot] = combine(&matrix[pivot], 1, &matrix[col], -q)?;
+    matrix.swap(pivot, col);
+    Ok(())
 }
 
 // read claw machine configurations
 fn read_data(filename: &str) -> Vec<Machine> {
-    let mut machines = Vec::new();
+    read_general_data(filename)
+        .iter()
+        .enumerate()
+        .map(|(idx, m)| {
+            m.to_machine()
+                .unwrap_or_else(|e| panic!("Block {}: {}", idx + 1, e))
+        })
+        .collect()
+}
+
+// read claw machine configurations with any amount of buttons and
+// dimensions
+fn read_general_data(filename: &str) -> Vec<GeneralMachine> {
     let data = read_to_string(filename).expect("File not found");
-    for machine in data.split("\n\n").collect::<Vec<&str>>() {
-        let lines = machine.split("\n").collect::<Vec<&str>>();
-        let button_x = Regex::new(r"X\+\d+").unwrap();
-        let button_y = Regex::new(r"Y\+\d+").unwrap();
-        let prize_x = Regex::new(r"X\=\d+").unwrap();
-        let prize_y = Regex::new(r"Y\=\d+").unwrap();
-        let (mut ax, mut ay, mut bx, mut by, mut px, mut py) = (0, 0, 0, 0, 0, 0);
-        for line in &lines {
-            if line.contains("Button A") {
-                ax = button_x.find(line).expect("Button A X not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
-                ay = button_y.find(line).expect("Button A Y not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
-            }
-            if line.contains("Button B") {
-                bx = button_x.find(line).expect("Button B X not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
-                by = button_y.find(line).expect("Button B Y not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
+    parse_machines(&data).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// a parsed button line: name, movement per axis and optional cost
+type ButtonLine = (char, Vec<(char, i64)>, Option<i64>);
+
+// parse claw machine configurations, separated by blank lines
+// - a button line looks like "Button C: X+1, Y-2, Z+3, Cost=2", without a
+//   cost button A costs 3 and button B costs 1 token
+// - the prize line looks like "Prize: X=10, Y=-20, Z=30" and defines the
+//   dimensions, each button needs to move in all of them
+// - line endings can be LF or CRLF, white space around fields is ignored
+// - errors name the block (counting from 1) and the field
+fn parse_machines(data: &str) -> Result<Vec<GeneralMachine>, String> {
+    let line_re = Regex::new(r"^(?:Button\s+([A-Z])|(Prize))\s*:(.*)$").unwrap();
+    let move_re = Regex::new(r"^([A-Z])\s*([+-]\s*\d+)$").unwrap();
+    let prize_re = Regex::new(r"^([A-Z])\s*=\s*(-?\s*\d+)$").unwrap();
+    let cost_re = Regex::new(r"^Cost\s*=\s*(\d+)$").unwrap();
+    let number = |value: &str| -> Result<i64, String> {
+        value
+            .split_whitespace()
+            .collect::<String>()
+            .trim_start_matches('+')
+            .parse::<i64>()
+            .map_err(|e| format!("{} ({})", value, e))
+    };
+    let mut blocks: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in data.lines().map(|l| l.trim()) {
+        if !line.is_empty() {
+            blocks.last_mut().unwrap().push(line);
+        } else if !blocks.last().unwrap().is_empty() {
+            blocks.push(Vec::new());
+        }
+    }
+    blocks.retain(|b| !b.is_empty());
+
+    let mut machines = Vec::new();
+    for (idx, lines) in blocks.iter().enumerate() {
+        let block = idx + 1;
+        let mut buttons: Vec<ButtonLine> = Vec::new();
+        let mut goal: Option<Vec<(char, i64)>> = None;
+        for line in lines {
+            let captures = line_re
+                .captures(line)
+                .ok_or_else(|| format!("Block {}: malformed line \"{}\"", block, line))?;
+            let fields = captures[3]
+                .split(',')
+                .map(|f| f.trim())
+                .collect::<Vec<&str>>();
+            if let Some(name) = captures.get(1) {
+                let name = name.as_str().chars().next().unwrap();
+                if buttons.iter().any(|b| b.0 == name) {
+                    return Err(format!("Block {}: Button {} is defined twice", block, name));
+                }
+                let mut moves = Vec::new();
+                let mut price = None;
+                for field in fields {
+                    if let Some(c) = move_re.captures(field) {
+                        let axis = c[1].chars().next().unwrap();
+                        let value = number(&c[2]).map_err(|e| {
+                            format!(
+                                "Block {}: Button {} {} is malformed: {}",
+                                block, name, axis, e
+                            )
+                        })?;
+                        if moves.iter().any(|m: &(char, i64)| m.0 == axis) {
+                            return Err(format!(
+                                "Block {}: Button {} moves in {} twice",
+                                block, name, axis
+                            ));
+                        }
+                        moves.push((axis, value));
+                    } else if let Some(c) = cost_re.captures(field) {
+                        price = Some(number(&c[1]).map_err(|e| {
+                            format!("Block {}: Button {} Cost is malformed: {}", block, name, e)
+                        })?);
+                    } else {
+                        return Err(format!(
+                            "Block {}: Button {} has a malformed field \"{}\"",
+                            block, name, field
+                        ));
+                    }
+                }
+                buttons.push((name, moves, price));
+            } else {
+                if goal.is_some() {
+                    return Err(format!("Block {}: Prize is defined twice", block));
+                }
+                let mut prize = Vec::new();
+                for field in fields {
+                    let c = prize_re.captures(field).ok_or_else(|| {
+                        format!("Block {}: Prize has a malformed field \"{}\"", block, field)
+                    })?;
+                    let axis = c[1].chars().next().unwrap();
+                    let value = number(&c[2]).map_err(|e| {
+                        format!("Block {}: Prize {} is malformed: {}", block, axis, e)
+                    })?;
+                    if prize.iter().any(|p: &(char, i64)| p.0 == axis) {
+                        return Err(format!("Block {}: Prize has {} twice", block, axis));
+                    }
+                    prize.push((axis, value));
+                }
+                goal = Some(prize);
             }
-            if line.contains("Prize") {
-                px = prize_x.find(line).expect("Button B X not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
-                py = prize_y.find(line).expect("Button B Y not found").as_str()[2..]
-                    .parse::<i64>()
-                    .expect("Couldn't convert to number");
+        }
+        let goal = goal.ok_or_else(|| format!("Block {}: Prize is missing", block))?;
+        if buttons.is_empty() {
+            return Err(format!("Block {}: Buttons are missing", block));
+        }
+        let axes = goal.iter().map(|g| g.0).collect::<Vec<char>>();
+        let mut machine_buttons = Vec::new();
+        for (name, moves, price) in buttons {
+            if let Some(m) = moves.iter().find(|m| !axes.contains(&m.0)) {
+                return Err(format!(
+                    "Block {}: Button {} moves in {}, the prize doesn't",
+                    block, name, m.0
+                ));
             }
+            let moves = axes
+                .iter()
+                .map(|axis| {
+                    moves
+                        .iter()
+                        .find(|m| m.0 == *axis)
+                        .map(|m| m.1)
+                        .ok_or_else(|| {
+                            format!("Block {}: Button {} {} is missing", block, name, axis)
+                        })
+                })
+                .collect::<Result<Vec<i64>, String>>()?;
+            let cost = match (price, name) {
+                (Some(price), _) => price,
+                (None, 'A') => 3,
+                (None, 'B') => 1,
+                (None, _) => {
+                    return Err(format!("Block {}: Button {} Cost is missing", block, name))
+                }
+            };
+            machine_buttons.push(Button { name, moves, cost });
         }
-        machines.push(Machine::new((ax, ay), (bx, by), (px, py)));
+        machines.push(GeneralMachine {
+            axes,
+            buttons: machine_buttons,
+            prize: goal.iter().map(|g| g.1).collect(),
+        });
     }
-    machines
+    Ok(machines)
 }
 
 #[cfg(test)]
@@ -124,4 +865,179 @@ mod tests {
         let price: i64 = machines.iter_mut().map(|m| get_price(m, true)).sum();
         assert_eq!(price, 96979582619758);
     }
+    #[test]
+    fn collinear() {
+        let config = Config::new(3, 1, None);
+        let m = Machine::new((2, 2), (1, 1), (10, 10));
+        assert_eq!(solve(&m, &config), Some((0, 10)));
+        assert_eq!(get_tokens(&m, &config), 10);
+        let config = Config::new(1, 3, None);
+        assert_eq!(solve(&m, &config), Some((5, 0)));
+        assert_eq!(get_tokens(&m, &config), 5);
+        // the prize isn't on the line
+        let m = Machine::new((2, 2), (1, 1), (10, 11));
+        assert_eq!(solve(&m, &config), None);
+        // 2a + 3b = 7 only has a non-negative solution with a = 2 and b = 1
+        let m = Machine::new((4, 6), (6, 9), (14, 21));
+        assert_eq!(solve(&m, &config), Some((2, 1)));
+        let m = Machine::new((4, 6), (6, 9), (15, 21));
+        assert_eq!(solve(&m, &config), None);
+        // a button that doesn't move
+        let m = Machine::new((0, 0), (3, 1), (9, 3));
+        assert_eq!(solve(&m, &config), Some((0, 3)));
+        let m = Machine::new((0, 0), (0, 0), (0, 0));
+        assert_eq!(solve(&m, &config), Some((0, 0)));
+    }
+    #[test]
+    fn limit() {
+        let m = Machine::new((2, 2), (1, 1), (300, 300));
+        assert_eq!(solve(&m, &Config::new(3, 1, Some(100))), Some((100, 100)));
+        assert_eq!(solve(&m, &Config::new(3, 1, None)), Some((0, 300)));
+        let m = Machine::new((1, 0), (0, 1), (101, 5));
+        assert_eq!(solve(&m, &Config::new(3, 1, Some(100))), None);
+        assert_eq!(solve(&m, &Config::new(3, 1, None)), Some((101, 5)));
+        let mut m = Machine::new((1, 0), (0, 1), (101, 5));
+        assert_eq!(get_price(&mut m, false), 0);
+    }
+    #[test]
+    fn general() {
+        let machines = read_general_data("input.test");
+        let price: i64 = machines
+            .iter()
+            .map(|m| get_general_tokens(m, Some(100)))
+            .sum::<Result<i64, String>>()
+            .unwrap();
+        assert_eq!(price, 480);
+        let mut machines = read_general_data("input");
+        let price: i64 = machines
+            .iter()
+            .map(|m| get_general_tokens(m, Some(100)))
+            .sum::<Result<i64, String>>()
+            .unwrap();
+        assert_eq!(price, 28887);
+        machines.iter_mut().for_each(|m| {
+            m.prize.iter_mut().for_each(|p| *p += 10000000000000);
+        });
+        let price = machines
+            .iter()
+            .map(|m| get_general_tokens(m, None))
+            .sum::<Result<i64, String>>()
+            .unwrap();
+        assert_eq!(price, 96979582619758);
+
+        let machines = read_general_data("input2.test");
+        assert_eq!(machines[1].axes, vec!['X', 'Y', 'Z']);
+        assert_eq!(machines[2].buttons[3].cost, 2);
+        assert_eq!(solve_general(&machines[0], None), Ok(Some(vec![0, 0, 5])));
+        assert_eq!(solve_general(&machines[1], None), Ok(Some(vec![2, 3, 4])));
+        assert_eq!(
+            solve_general(&machines[2], None),
+            Ok(Some(vec![0, 1, 1, 3]))
+        );
+        assert_eq!(solve_general(&machines[3], None), Ok(None));
+        let price = machines
+            .iter()
+            .map(|m| get_general_tokens(m, None))
+            .sum::<Result<i64, String>>()
+            .unwrap();
+        assert_eq!(price, 32);
+        assert_eq!(
+            solve_general(&machines[2], Some(2)),
+            Ok(Some(vec![1, 1, 2, 2]))
+        );
+    }
+    #[test]
+    fn several_free_buttons() {
+        // button C is as good as A and B together
+        let data = "Button A: X+94, Y+34\nButton B: X+22, Y+67\nButton C: X+116, Y+101, Cost=4\nButton D: X+50, Y+50, Cost=5\nPrize: X=10000000008400, Y=10000000005400";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(get_general_tokens(&machines[0], None), Ok(351351351680));
+        // five buttons, the echelon form alone gives numbers beyond i128
+        let data = "Button A: X+97, Y+13\nButton B: X+89, Y+71\nButton C: X+83, Y+5, Cost=2\nButton D: X+7, Y+61, Cost=5\nButton E: X+11, Y+12, Cost=2\nPrize: X=20765431992340, Y=8617283937359";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(
+            solve_general(&machines[0], None),
+            Ok(Some(vec![59, 112226029146, 129847173404, 2, 7]))
+        );
+        assert_eq!(get_general_tokens(&machines[0], None), Ok(371920376155));
+        // a button that doesn't move is never pressed
+        let data = "Button A: X+1, Y+0\nButton B: X+0, Y+1\nButton C: X+0, Y+0, Cost=2\nButton D: X+1, Y+1, Cost=1\nPrize: X=5, Y=7";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(
+            solve_general(&machines[0], None),
+            Ok(Some(vec![0, 2, 0, 5]))
+        );
+        assert_eq!(solve_general(&machines[0], Some(3)), Ok(None));
+        // moves in both directions
+        let data = "Button A: X+1, Y+0\nButton B: X-1, Y+1\nButton C: X+1, Y+1, Cost=2\nButton D: X+2, Y+1, Cost=2\nPrize: X=5, Y=5";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(
+            solve_general(&machines[0], None),
+            Ok(Some(vec![0, 1, 2, 2]))
+        );
+        assert_eq!(solve_general(&machines[0], Some(1)), Ok(None));
+        // buttons A and B together get back to the start
+        let data = "Button A: X+2, Y+0, Cost=1\nButton B: X-1, Y+0, Cost=1\nButton C: X+0, Y+1, Cost=1\nButton D: X+1, Y+1, Cost=2\nPrize: X=3, Y=4";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(
+            solve_general(&machines[0], None),
+            Ok(Some(vec![1, 0, 3, 1]))
+        );
+        let data = "Button A: X+1, Y+0, Cost=1\nButton B: X-1, Y+0, Cost=1\nButton C: X+0, Y+2, Cost=1\nButton D: X+0, Y+3, Cost=1\nPrize: X=0, Y=1";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(solve_general(&machines[0], None), Ok(None));
+    }
+    #[test]
+    fn parser() {
+        let data = read_to_string("input.test").expect("File not found");
+        let crlf = format!(
+            "\r\n\r\n{}\r\n\r\n\r\n",
+            data.replace("\n", "\r\n")
+                .replace("\r\n\r\n", "\r\n \t\r\n\r\n")
+        );
+        let machines = parse_machines(&crlf).unwrap();
+        assert_eq!(machines.len(), 4);
+        assert_eq!(machines[3].prize, vec![18641, 10279]);
+        assert_eq!(machines[3].buttons[1].moves, vec![27, 71]);
+
+        let data = "Button A:X-12 ,  Y+3\nButton B: X + 4, Y - 1\nPrize: X=-4, Y= 1";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(machines[0].buttons[0].moves, vec![-12, 3]);
+        assert_eq!(machines[0].buttons[1].moves, vec![4, -1]);
+        assert_eq!(machines[0].prize, vec![-4, 1]);
+        let m = machines[0].to_machine().unwrap();
+        assert_eq!(solve(&m, &Config::new(3, 1, None)), Some((1, 2)));
+        assert_eq!(solve_general(&machines[0], None), Ok(Some(vec![1, 2])));
+
+        let data = "Button B: X+2, Y+1\nButton A: X+1, Y+3\nPrize: Y=5, X=5";
+        let machines = parse_machines(data).unwrap();
+        assert_eq!(machines[0].axes, vec!['Y', 'X']);
+        let m = machines[0].to_machine().unwrap();
+        assert_eq!((m.a, m.b, m.prize), ((1, 3), (2, 1), (5, 5)));
+        assert_eq!(solve(&m, &Config::new(3, 1, None)), Some((1, 2)));
+    }
+    #[test]
+    fn parser_errors() {
+        let errors = [
+            ("Button A: X+1, Y+2\nButton B: X+3\nPrize: X=4, Y=6", "Block 1: Button B Y is missing"),
+            ("Button A: X+1, Y+2\nButton B: X+3, Y+1\nPrize: X=4, Y=3\n\n\nButton A: X+1, Y+2\nButton B: X+3, Y+1", "Block 2: Prize is missing"),
+            ("Button A: X+1, Y+2\nButton B: X*3, Y+1\nPrize: X=4, Y=6", "Block 1: Button B has a malformed field \"X*3\""),
+            ("Button A: X+1, Y+2\nButton B: X+3, Y+1\nPrize: X=4, Y=six", "Block 1: Prize has a malformed field \"Y=six\""),
+            ("Button A: X+1, Y+2\nButton B: X+3, Y+1\nPrice: X=4, Y=6", "Block 1: malformed line \"Price: X=4, Y=6\""),
+            ("Button A: X+1, Y+2\nButton C: X+3, Y+1\nPrize: X=4, Y=6", "Block 1: Button C Cost is missing"),
+            ("Button A: X+1, Y+2, Z+1\nButton B: X+3, Y+1\nPrize: X=4, Y=6", "Block 1: Button A moves in Z, the prize doesn't"),
+            ("Button A: X+1, X+2\nPrize: X=4", "Block 1: Button A moves in X twice"),
+            ("Button A: X+99999999999999999999\nPrize: X=4", "Block 1: Button A X is malformed: +99999999999999999999 (number too large to fit in target type)"),
+        ];
+        for (data, error) in errors {
+            assert_eq!(parse_machines(data).err(), Some(error.to_string()));
+        }
+        let machines =
+            parse_machines("Button A: X+1, Y+2\nButton C: X+3, Y+1, Cost=1\nPrize: X=4, Y=3")
+                .unwrap();
+        assert_eq!(
+            machines[0].to_machine().err(),
+            Some("Machine needs buttons A and B".to_string())
+        );
+    }
 }
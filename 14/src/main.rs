@@ -7,34 +7,135 @@
 // - the robots move in a 101x103 big area (it's 11x7 in the test)
 // - 0,0 is the top left corner of the area
 // - robots teleport to the other side when they reach the edge of the area
+// - other arenas are supported as well
+//   - bounded walls, robots bounce back when they hit a wall
+//   - a Klein bottle, robots wrapping around vertically come back mirrored
+// - positions are calculated in closed form for any time t (even negative or
+//   huge ones), without simulating the steps in between
 // - part 1:
 //   - calculate the amount of robots in each quadrant (safety factor)
 //     - robots on the horizontal or vertical center line are ignored
 //     - count the amount of robots in each quadrant
 //     - multiply the quadrant sums for the result
+//   - the area can also be split into N x M parts, the safety factor is the
+//     product of their robot counts, robots on a tile a dividing line runs
+//     through are ignored
 // - part 2:
 //   - there's an Easter egg where the robots form a Christmas tree
 //     - this somehow means all robots are on a distinct spot and there are no
 //       overlaps
 //     - count the amount of steps it takes until the Easter egg happens
+//   - the positions repeat after width * height steps, so only one period is
+//     searched
+//   - a statistical detector ranks candidate times, the tree is an unusually
+//     ordered frame
+//     - low x and y variance, the x and y periods are scored on their own and
+//       combined with the Chinese remainder theorem
+//     - low safety factor, most robots are in one quadrant
+//     - small run-length encoded frame
+//     - large connected cluster of robots
+//   - frames can be exported as PBM or PNG images and as an animated GIF to
+//     review the candidates, tiles with more than one robot are highlighted,
+//     run with `export <output file> <times> [scale]`
+//     - the format is taken from the file extension (pbm, png or gif)
+//     - the times are a comma separated list of time steps or ranges of them,
+//       like 7340-7350,8000, images take a single time step
 
-use std::fs::File;
-use std::io::{self, BufRead};
+use std::borrow::Cow;
+use std::fs::{write, File};
+use std::io::{self, BufRead, BufWriter};
 use std::path::Path;
 
 fn main() {
     let map = (101, 103);
     let mut robots = read_data("input", map);
-    for robot in &mut robots {
-        robot.nstep(100);
-    }
+    move_robots(&mut robots, 100);
     let safety_factor = calc_safety(&robots, &map);
     println!("The safety factor is {}", safety_factor);
+    let safety_factor = calc_partition_safety(&robots, &map, (3, 3));
+    assert_eq!(safety_factor, 2228995692000000);
+    println!("The safety factor of a 3x3 partition is {}", safety_factor);
+    for topology in [Topology::Walls, Topology::Klein] {
+        let mut robots = read_topology_data("input", map, topology);
+        move_robots(&mut robots, 100);
+        let safety_factor = calc_safety(&robots, &map);
+        println!("The safety factor with {:?} is {}", topology, safety_factor);
+    }
+
+    let robots = read_data("input", map);
+    let candidates = find_easter_egg(&robots, &map, 3);
+    assert_eq!(candidates[0].0, 7344);
+    println!(
+        "The best candidates by CRT of the variances are {}",
+        format_candidates(&candidates)
+    );
+    let frames = scan_period(&robots, &map);
+    for metric in Metric::all() {
+        let candidates = rank_frames(&frames, metric, 3);
+        assert_eq!(candidates[0].0, 7344);
+        println!(
+            "The best candidates by {:?} are {}",
+            metric,
+            format_candidates(&candidates)
+        );
+    }
 
     let mut robots = read_data("input", map);
-    let easter_egg_steps = calc_easter_egg(&mut robots);
+    let easter_egg_steps = calc_easter_egg(&mut robots, &map).expect("No Easter egg found");
     println!("The easter egg happens after {} steps", easter_egg_steps);
     print_positions(&robots, &map);
+
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(|a| a.as_str()) == Some("export") {
+        let output = Path::new(args.get(2).expect("Output file needed"));
+        let times = parse_times(args.get(3).expect("Time steps needed"));
+        let scale = args.get(4).map_or(2, |a| a.parse().expect("Invalid scale"));
+        let robots = read_data("input", map);
+        let single = || match times[..] {
+            [time] => Image::new(&robots, &map, time, scale),
+            _ => panic!("Images need a single time step"),
+        };
+        match output.extension().and_then(|e| e.to_str()) {
+            Some("pbm") => write(output, single().to_pbm()).expect("Couldn't write PBM"),
+            Some("png") => write_png(output, &single()).expect("Couldn't write PNG"),
+            Some("gif") => {
+                write_gif(output, &robots, &map, &times, scale, 100).expect("Couldn't write GIF")
+            }
+            _ => panic!(
+                "Unknown format of {}, use pbm, png or gif",
+                output.display()
+            ),
+        }
+        println!(
+            "{} frames were written to {}",
+            times.len(),
+            output.display()
+        );
+    }
+}
+
+// how robots move at the edges of the area
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Topology {
+    // teleport to the other side
+    Torus,
+    // bounce back, the velocity is reversed
+    Walls,
+    // teleport to the other side horizontally, vertically come back on the
+    // mirrored column with the horizontal velocity reversed
+    Klein,
+}
+impl Topology {
+    // the amount of steps after which all robots are back in their start
+    // state
+    fn period(&self, map: &(i64, i64)) -> i64 {
+        let lcm = |a: i64, b: i64| a / gcd(a, b) * b;
+        match self {
+            Topology::Torus => lcm(map.0, map.1),
+            Topology::Walls => lcm((2 * (map.0 - 1)).max(1), (2 * (map.1 - 1)).max(1)),
+            Topology::Klein => lcm(map.0, 2 * map.1),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -42,72 +143,350 @@ struct Robot {
     pos: (i64, i64),
     vel: (i64, i64),
     map: (i64, i64),
+    topology: Topology,
 }
 impl Robot {
     fn new(pos: (i64, i64), vel: (i64, i64), map: (i64, i64)) -> Self {
-        Self { pos, vel, map }
-    }
-    fn step(&mut self) {
-        self.pos.0 += self.vel.0;
-        if self.pos.0 < 0 {
-            self.pos.0 += self.map.0;
-        }
-        if self.pos.0 > self.map.0 - 1 {
-            self.pos.0 %= self.map.0;
-        }
-        self.pos.1 += self.vel.1;
-        if self.pos.1 < 0 {
-            self.pos.1 += self.map.1;
-        }
-        if self.pos.1 > self.map.1 - 1 {
-            self.pos.1 %= self.map.1;
+        Self {
+            pos,
+            vel,
+            map,
+            topology: Topology::Torus,
         }
     }
-    fn nstep(&mut self, step: usize) {
-        for _ in 0..step {
-            self.step()
+    fn with_topology(self, topology: Topology) -> Self {
+        Self { topology, ..self }
+    }
+    // position at time t
+    fn position_at(&self, t: i64) -> (i64, i64) {
+        self.state_at(t).0
+    }
+    // position and velocity at time t, the robot moves on a straight line
+    // that is folded back into the area
+    // - the 128 bit intermediates can't overflow for any i64 time
+    // - on a torus, the wrap around is the Euclidean remainder
+    // - with walls, the line is wrapped on twice the area and the second half
+    //   is mirrored, a robot on a wall always moves away from it
+    // - on a Klein bottle, every vertical wrap around mirrors the column
+    fn state_at(&self, t: i64) -> ((i64, i64), (i64, i64)) {
+        let line = |pos: i64, vel: i64| pos as i128 + vel as i128 * t as i128;
+        let wrap = |pos: i64, vel: i64, size: i64| line(pos, vel).rem_euclid(size as i128) as i64;
+        let bounce = |pos: i64, vel: i64, size: i64| {
+            if size == 1 {
+                return (0, vel);
+            }
+            let edge = size - 1;
+            let folded = wrap(pos, vel, 2 * edge);
+            if folded == 0 {
+                (0, vel.abs())
+            } else if folded == edge {
+                (edge, -vel.abs())
+            } else if folded > edge {
+                (2 * edge - folded, -vel)
+            } else {
+                (folded, vel)
+            }
+        };
+        let (pos, vel, map) = (self.pos, self.vel, self.map);
+        match self.topology {
+            Topology::Torus => ((wrap(pos.0, vel.0, map.0), wrap(pos.1, vel.1, map.1)), vel),
+            Topology::Walls => {
+                let (x, vx) = bounce(pos.0, vel.0, map.0);
+                let (y, vy) = bounce(pos.1, vel.1, map.1);
+                ((x, y), (vx, vy))
+            }
+            Topology::Klein => {
+                let x = wrap(pos.0, vel.0, map.0);
+                let y = wrap(pos.1, vel.1, map.1);
+                let wraps = line(pos.1, vel.1).div_euclid(map.1 as i128);
+                if wraps % 2 == 0 {
+                    ((x, y), vel)
+                } else {
+                    ((map.0 - 1 - x, y), (-vel.0, vel.1))
+                }
+            }
         }
     }
+    #[cfg(test)]
+    fn step(&mut self) {
+        self.nstep(1);
+    }
+    fn nstep(&mut self, step: i64) {
+        (self.pos, self.vel) = self.state_at(step);
+    }
+}
+
+// get the positions of all robots at time t
+fn positions_at(robots: &[Robot], t: i64) -> Vec<(i64, i64)> {
+    robots.iter().map(|r| r.position_at(t)).collect()
+}
+
+// move all robots to their positions at time t
+fn move_robots(robots: &mut [Robot], t: i64) {
+    for robot in robots.iter_mut() {
+        robot.nstep(t);
+    }
 }
 
 // calculate the safety factor
 fn calc_safety(robots: &[Robot], map: &(i64, i64)) -> usize {
-    let (mut q1, mut q2, mut q3, mut q4): (usize, usize, usize, usize) = (0, 0, 0, 0);
-    for robot in robots {
-        if robot.pos.0 < map.0 / 2 && robot.pos.1 < map.1 / 2 {
-            q1 += 1;
+    calc_partition_safety(robots, map, (2, 2))
+}
+
+// calculate the safety factor of the area split into parts.0 x parts.1 parts
+fn calc_partition_safety(robots: &[Robot], map: &(i64, i64), parts: (i64, i64)) -> usize {
+    let positions = robots.iter().map(|r| r.pos).collect::<Vec<(i64, i64)>>();
+    partition(&positions, map, parts).iter().product()
+}
+
+// calculate the safety factor of a list of positions
+fn safety_factor(positions: &[(i64, i64)], map: &(i64, i64)) -> usize {
+    partition(positions, map, (2, 2)).iter().product()
+}
+
+// count the robots in each part of the area split into parts.0 x parts.1
+// parts, row by row
+// - the dividing lines are at size * k / parts, robots on a tile a line
+//   runs through are ignored, a line on the edge between two tiles doesn't
+//   run through either of them
+fn partition(positions: &[(i64, i64)], map: &(i64, i64), parts: (i64, i64)) -> Vec<usize> {
+    // the part of a coordinate, None if it's on a dividing line
+    let part = |pos: i64, size: i64, parts: i64| {
+        // the first line after the left edge of the tile
+        let line = pos * parts / size + 1;
+        if line < parts && line * size < (pos + 1) * parts {
+            None
+        } else {
+            Some(pos * parts / size)
         }
-        if robot.pos.0 > map.0 / 2 && robot.pos.1 < map.1 / 2 {
-            q2 += 1;
+    };
+    let mut counts = vec![0; (parts.0 * parts.1) as usize];
+    for pos in positions {
+        if let (Some(x), Some(y)) = (part(pos.0, map.0, parts.0), part(pos.1, map.1, parts.1)) {
+            counts[(y * parts.0 + x) as usize] += 1;
         }
-        if robot.pos.0 < map.0 / 2 && robot.pos.1 > map.1 / 2 {
-            q3 += 1;
+    }
+    counts
+}
+
+// calculate after how many steps the Easter egg happens
+// - the first time in the period where every robot is on a unique position,
+//   None if this never happens
+fn calc_easter_egg(robots: &mut [Robot], map: &(i64, i64)) -> Option<usize> {
+    let steps =
+        (0..Topology::Torus.period(map)).find(|t| unique_positions(&positions_at(robots, *t)))?;
+    move_robots(robots, steps);
+    Some(steps as usize)
+}
+
+// is every robot on a unique position?
+fn unique_positions(positions: &[(i64, i64)]) -> bool {
+    let mut distinct = positions.to_vec();
+    distinct.sort();
+    distinct.dedup();
+    distinct.len() == positions.len()
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// combine t = a.0 mod a.1 and t = b.0 mod b.1 into t mod lcm(a.1, b.1)
+fn crt(a: (i64, i64), b: (i64, i64)) -> Option<(i64, i64)> {
+    let (g, p, _) = extended_gcd(a.1, b.1);
+    if (b.0 - a.0) % g != 0 {
+        return None;
+    }
+    let lcm = a.1 / g * b.1;
+    let k = ((b.0 - a.0) / g) as i128 * p as i128 % (b.1 / g) as i128;
+    let t = (a.0 as i128 + a.1 as i128 * k).rem_euclid(lcm as i128) as i64;
+    Some((t, lcm))
+}
+
+// get gcd(a, b) and x, y with a * x + b * y = gcd(a, b)
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - a / b * y)
+    }
+}
+
+// ways to score how ordered a frame is
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    Variance,
+    Safety,
+    Compression,
+    Cluster,
+}
+impl Metric {
+    fn all() -> [Metric; 4] {
+        [
+            Metric::Variance,
+            Metric::Safety,
+            Metric::Compression,
+            Metric::Cluster,
+        ]
+    }
+}
+
+// statistics of the robot positions at one time
+#[derive(Debug)]
+struct Frame {
+    time: i64,
+    variance: (f64, f64),
+    safety: usize,
+    compressed: usize,
+    cluster: usize,
+}
+impl Frame {
+    fn new(robots: &[Robot], map: &(i64, i64), time: i64) -> Self {
+        let positions = positions_at(robots, time);
+        let xs = positions.iter().map(|p| p.0).collect::<Vec<i64>>();
+        let ys = positions.iter().map(|p| p.1).collect::<Vec<i64>>();
+        Self {
+            time,
+            variance: (variance(&xs), variance(&ys)),
+            safety: safety_factor(&positions, map),
+            compressed: compressed_size(&positions),
+            cluster: largest_cluster(&positions),
         }
-        if robot.pos.0 > map.0 / 2 && robot.pos.1 > map.1 / 2 {
-            q4 += 1;
+    }
+    // lower scores are more likely to be the Easter egg
+    fn score(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Variance => self.variance.0 + self.variance.1,
+            Metric::Safety => self.safety as f64,
+            Metric::Compression => self.compressed as f64,
+            Metric::Cluster => -(self.cluster as f64),
         }
     }
-    q1 * q2 * q3 * q4
 }
 
-// calculate after how many steps the Easter egg happens
-fn calc_easter_egg(robots: &mut [Robot]) -> usize {
-    let mut steps = 0;
-    while !unique_positions(robots) {
-        for robot in &mut *robots {
-            robot.step();
+fn variance(values: &[i64]) -> f64 {
+    let n = values.len().max(1) as f64;
+    let mean = values.iter().sum::<i64>() as f64 / n;
+    values
+        .iter()
+        .map(|v| (*v as f64 - mean).powi(2))
+        .sum::<f64>()
+        / n
+}
+
+// size of the run-length encoded frame, every run of occupied tiles in a row
+// needs its gap and its length
+fn compressed_size(positions: &[(i64, i64)]) -> usize {
+    let mut cells = positions
+        .iter()
+        .map(|p| (p.1, p.0))
+        .collect::<Vec<(i64, i64)>>();
+    cells.sort();
+    cells.dedup();
+    let joined = cells
+        .windows(2)
+        .filter(|w| w[0].0 == w[1].0 && w[0].1 + 1 == w[1].1)
+        .count();
+    2 * (cells.len() - joined)
+}
+
+// amount of tiles in the biggest group of horizontally or vertically
+// connected robots
+fn largest_cluster(positions: &[(i64, i64)]) -> usize {
+    let mut cells = positions.to_vec();
+    cells.sort();
+    cells.dedup();
+    let mut visited = vec![false; cells.len()];
+    let mut largest = 0;
+    for start in 0..cells.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut size = 0;
+        while let Some(idx) = stack.pop() {
+            size += 1;
+            let (x, y) = cells[idx];
+            for next in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if let Ok(next) = cells.binary_search(&next) {
+                    if !visited[next] {
+                        visited[next] = true;
+                        stack.push(next);
+                    }
+                }
+            }
         }
-        steps += 1;
+        largest = largest.max(size);
     }
-    steps
+    largest
 }
 
-// is every robot on a unique position?
-fn unique_positions(robots: &[Robot]) -> bool {
-    let mut positions: Vec<(i64, i64)> = robots.iter().map(|p| p.pos).collect::<Vec<(i64, i64)>>();
-    positions.sort();
-    positions.dedup();
-    positions.len() == robots.len()
+// score every frame of one period
+fn scan_period(robots: &[Robot], map: &(i64, i64)) -> Vec<Frame> {
+    (0..Topology::Torus.period(map))
+        .map(|t| Frame::new(robots, map, t))
+        .collect()
+}
+
+// list candidate times with their score, as "time (score)"
+fn format_candidates(candidates: &[(i64, f64)]) -> String {
+    candidates
+        .iter()
+        .map(|(time, score)| format!("{} ({:.1})", time, score))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+// rank frames by how likely they show the Easter egg, the best `count`
+// candidate times are returned with their score
+fn rank_frames(frames: &[Frame], metric: Metric, count: usize) -> Vec<(i64, f64)> {
+    let mut candidates = frames
+        .iter()
+        .map(|f| (f.time, f.score(metric)))
+        .collect::<Vec<(i64, f64)>>();
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    candidates.truncate(count);
+    candidates
+}
+
+// find the best `count` candidate times for the Easter egg by their variance
+// without scoring every frame
+// - the x positions repeat with the width and the y positions with the
+//   height, so the best x and y offsets are combined with the CRT
+fn find_easter_egg(robots: &[Robot], map: &(i64, i64), count: usize) -> Vec<(i64, f64)> {
+    let ranked = |size: i64, axis: fn(&(i64, i64)) -> i64| {
+        let mut offsets = (0..size)
+            .map(|t| {
+                let values = positions_at(robots, t)
+                    .iter()
+                    .map(axis)
+                    .collect::<Vec<i64>>();
+                (t, variance(&values))
+            })
+            .collect::<Vec<(i64, f64)>>();
+        offsets.sort_by(|a, b| a.1.total_cmp(&b.1));
+        offsets.truncate(count);
+        offsets
+    };
+    let xs = ranked(map.0, |p| p.0);
+    let ys = ranked(map.1, |p| p.1);
+    let mut candidates = Vec::new();
+    for x in &xs {
+        for y in &ys {
+            if let Some((t, _)) = crt((x.0, map.0), (y.0, map.1)) {
+                candidates.push((t, x.1 + y.1));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    candidates.truncate(count);
+    candidates
 }
 
 // print the map with robot positions to see the Easter egg
@@ -125,8 +504,116 @@ fn print_positions(robots: &[Robot], map: &(i64, i64)) {
     }
 }
 
+// colours of the exported images: empty tile, one robot, overlapping robots
+const PALETTE: [u8; 9] = [255, 255, 255, 0, 128, 0, 255, 0, 0];
+
+// the robot positions at one time as an image, every pixel is the index of
+// its colour in the palette
+struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+impl Image {
+    // every tile is drawn as a `scale` x `scale` square
+    fn new(robots: &[Robot], map: &(i64, i64), time: i64, scale: usize) -> Self {
+        let (width, height) = (map.0 as usize * scale, map.1 as usize * scale);
+        let mut tiles = vec![0u8; (map.0 * map.1) as usize];
+        for (x, y) in positions_at(robots, time) {
+            let tile = &mut tiles[(y * map.0 + x) as usize];
+            *tile = (*tile + 1).min(2);
+        }
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                pixels.push(tiles[y / scale * map.0 as usize + x / scale]);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+    // plain PBM, a bitmap can't highlight overlaps
+    fn to_pbm(&self) -> String {
+        let mut pbm = format!("P1\n{} {}\n", self.width, self.height);
+        for row in self.pixels.chunks(self.width) {
+            let bits = row
+                .iter()
+                .map(|p| if *p > 0 { "1" } else { "0" })
+                .collect::<Vec<&str>>();
+            pbm.push_str(&bits.join(" "));
+            pbm.push('\n');
+        }
+        pbm
+    }
+}
+
+// parse time steps like 7340-7350,8000, ranges include both ends
+fn parse_times(times: &str) -> Vec<i64> {
+    let number = |n: &str| n.trim().parse::<i64>().expect("Invalid time step");
+    times
+        .split(',')
+        .flat_map(|part| match part.split_once('-') {
+            Some((from, to)) => number(from)..=number(to),
+            None => number(part)..=number(part),
+        })
+        .collect()
+}
+
+// write an image as indexed PNG
+fn write_png(path: &Path, image: &Image) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(PALETTE.to_vec());
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image.pixels)?;
+    Ok(())
+}
+
+// write the frames at the given times as animated GIF, `delay` is the time
+// between frames in 1/100s
+fn write_gif(
+    path: &Path,
+    robots: &[Robot],
+    map: &(i64, i64),
+    times: &[i64],
+    scale: usize,
+    delay: u16,
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let (width, height) = (
+        (map.0 as usize * scale) as u16,
+        (map.1 as usize * scale) as u16,
+    );
+    let mut encoder = gif::Encoder::new(file, width, height, &PALETTE).map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+    for time in times {
+        let image = Image::new(robots, map, *time, scale);
+        let frame = gif::Frame {
+            width,
+            height,
+            delay,
+            buffer: Cow::Borrowed(&image.pixels),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
 // read robot configurations
 fn read_data(filename: &str, map: (i64, i64)) -> Vec<Robot> {
+    read_topology_data(filename, map, Topology::Torus)
+}
+
+// read robot configurations for an area with the given topology
+fn read_topology_data(filename: &str, map: (i64, i64), topology: Topology) -> Vec<Robot> {
     let mut machines = Vec::new();
     if let Ok(lines) = read_lines(filename) {
         for line in lines.map_while(Result::ok) {
@@ -150,7 +637,7 @@ fn read_data(filename: &str, map: (i64, i64)) -> Vec<Robot> {
             let y = pos[1].parse::<i64>().expect("Couldn't parse number");
             let vx = vel[0].parse::<i64>().expect("Couldn't parse number");
             let vy = vel[1].parse::<i64>().expect("Couldn't parse number");
-            machines.push(Robot::new((x, y), (vx, vy), map));
+            machines.push(Robot::new((x, y), (vx, vy), map).with_topology(topology));
         }
     }
     machines
@@ -199,6 +686,34 @@ mod tests {
         assert_eq!(robot.pos, (1, 3));
     }
     #[test]
+    fn closed_form() {
+        // velocities bigger than the map
+        let mut robot = Robot::new((1, 2), (25, -17), (3, 4));
+        let start = Robot::new((1, 2), (25, -17), (3, 4));
+        for t in 1..20 {
+            robot.pos = (
+                (robot.pos.0 + 25 % 3 + 3) % 3,
+                (robot.pos.1 - 17 % 4 + 4) % 4,
+            );
+            assert_eq!(start.position_at(t), robot.pos);
+        }
+        // back in time
+        assert_eq!(start.position_at(-1), (0, 3));
+        // huge times repeat with the period of the map
+        let robots = read_data("input", (101, 103));
+        let period = 101 * 103;
+        let huge = i64::MAX - i64::MAX % period;
+        assert_eq!(positions_at(&robots, huge), positions_at(&robots, 0));
+        assert_eq!(
+            positions_at(&robots, huge + 100),
+            positions_at(&robots, 100)
+        );
+        assert_eq!(positions_at(&robots, i64::MIN + 7), {
+            let t = (i64::MIN + 7).rem_euclid(period);
+            positions_at(&robots, t)
+        });
+    }
+    #[test]
     fn part1() {
         let mut robots = read_data("input.test", (11, 7));
         for robot in &mut robots {
@@ -208,15 +723,198 @@ mod tests {
 
         let map = (101, 103);
         let mut robots = read_data("input", map);
-        for robot in &mut robots {
-            robot.nstep(100);
-        }
+        move_robots(&mut robots, 100);
         assert_eq!(calc_safety(&robots, &map), 211773366);
     }
     #[test]
     fn part2() {
         let map = (101, 103);
         let mut robots = read_data("input", map);
-        assert_eq!(calc_easter_egg(&mut robots), 7344);
+        assert_eq!(calc_easter_egg(&mut robots, &map), Some(7344));
+    }
+    #[test]
+    fn no_easter_egg() {
+        // two robots that always share a spot
+        let map = (11, 7);
+        let mut robots = vec![
+            Robot::new((1, 2), (3, -4), map),
+            Robot::new((1, 2), (3, -4), map),
+        ];
+        assert_eq!(calc_easter_egg(&mut robots, &map), None);
+    }
+    #[test]
+    fn detector() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((1, 4), (2, 6)), None);
+        assert_eq!(Topology::Torus.period(&(101, 103)), 10403);
+        assert_eq!(Topology::Torus.period(&(4, 6)), 12);
+
+        let positions = vec![(0, 0), (1, 0), (2, 0), (2, 1), (5, 5), (5, 5)];
+        assert_eq!(largest_cluster(&positions), 4);
+        assert_eq!(compressed_size(&positions), 6);
+        assert_eq!(variance(&[1, 3]), 1.0);
+
+        let map = (11, 7);
+        let robots = read_data("input.test", map);
+        assert_eq!(scan_period(&robots, &map).len(), 77);
+
+        let map = (101, 103);
+        let robots = read_data("input", map);
+        assert_eq!(find_easter_egg(&robots, &map, 1)[0].0, 7344);
+        // scoring a whole period is slow without optimisations
+        let frames = (7000..8000)
+            .map(|t| Frame::new(&robots, &map, t))
+            .collect::<Vec<Frame>>();
+        for metric in Metric::all() {
+            assert_eq!(rank_frames(&frames, metric, 3)[0].0, 7344);
+        }
+    }
+    #[test]
+    fn export() {
+        // the initial state of the example
+        let map = (11, 7);
+        let robots = read_data("input.test", map);
+        let expected = [
+            "1.12.......",
+            "...........",
+            "...........",
+            "......11.11",
+            "1.1........",
+            ".........1.",
+            ".......1...",
+        ];
+        let image = Image::new(&robots, &map, 0, 1);
+        let tiles = expected
+            .iter()
+            .flat_map(|l| l.chars().map(|c| c.to_digit(10).unwrap_or(0).min(2) as u8))
+            .collect::<Vec<u8>>();
+        assert_eq!(image.pixels, tiles);
+        let pbm = image.to_pbm();
+        assert!(pbm.starts_with("P1\n11 7\n1 0 1 1 0 0 0 0 0 0 0\n"));
+        assert_eq!(pbm.lines().count(), 9);
+
+        let image = Image::new(&robots, &map, 0, 3);
+        assert_eq!((image.width, image.height), (33, 21));
+        assert_eq!(image.pixels[2 * 33 + 9..2 * 33 + 12], [2, 2, 2]);
+
+        let dir = std::env::temp_dir();
+        let path = dir.join("aoc24-14-test.png");
+        write_png(&path, &image).unwrap();
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (33, 21));
+        assert_eq!(pixels, image.pixels);
+
+        assert_eq!(parse_times("7344"), vec![7344]);
+        assert_eq!(parse_times("3-5, 1,8 - 9"), vec![3, 4, 5, 1, 8, 9]);
+        assert!(parse_times("5-3").is_empty());
+
+        let path = dir.join("aoc24-14-test.gif");
+        write_gif(&path, &robots, &map, &[0, 1, 2], 3, 50).unwrap();
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!((frame.width, frame.height, frame.delay), (33, 21, 50));
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+    }
+    #[test]
+    fn topologies() {
+        // simulate one step at a time, with velocities smaller than the area
+        let simulate = |robot: &Robot, steps: i64| {
+            let (mut pos, mut vel, map) = (robot.pos, robot.vel, robot.map);
+            for _ in 0..steps {
+                match robot.topology {
+                    Topology::Torus => {
+                        pos = (
+                            (pos.0 + vel.0).rem_euclid(map.0),
+                            (pos.1 + vel.1).rem_euclid(map.1),
+                        );
+                    }
+                    Topology::Walls => {
+                        // bounce off a wall when reaching or passing it
+                        let mut next = (pos.0 + vel.0, pos.1 + vel.1);
+                        if next.0 <= 0 && vel.0 < 0 || next.0 >= map.0 - 1 && vel.0 > 0 {
+                            next.0 = if next.0 <= 0 {
+                                -next.0
+                            } else {
+                                2 * (map.0 - 1) - next.0
+                            };
+                            vel.0 = -vel.0;
+                        }
+                        if next.1 <= 0 && vel.1 < 0 || next.1 >= map.1 - 1 && vel.1 > 0 {
+                            next.1 = if next.1 <= 0 {
+                                -next.1
+                            } else {
+                                2 * (map.1 - 1) - next.1
+                            };
+                            vel.1 = -vel.1;
+                        }
+                        pos = next;
+                    }
+                    Topology::Klein => {
+                        let next = ((pos.0 + vel.0).rem_euclid(map.0), pos.1 + vel.1);
+                        if next.1 < 0 || next.1 > map.1 - 1 {
+                            pos = (map.0 - 1 - next.0, next.1.rem_euclid(map.1));
+                            vel.0 = -vel.0;
+                        } else {
+                            pos = next;
+                        }
+                    }
+                }
+            }
+            (pos, vel)
+        };
+        for topology in [Topology::Torus, Topology::Walls, Topology::Klein] {
+            let map = (11, 7);
+            let mut robots = read_topology_data("input.test", map, topology);
+            let period = topology.period(&map);
+            for robot in &mut robots {
+                // robots on a wall at the start may get a normalised velocity
+                let start = robot.state_at(0);
+                for t in 1..100 {
+                    assert_eq!(robot.state_at(t), simulate(robot, t));
+                }
+                assert_eq!(robot.state_at(period), start);
+                assert_eq!(robot.state_at(-period), start);
+                let expected = simulate(robot, 50);
+                for _ in 0..50 {
+                    robot.step();
+                }
+                assert_eq!((robot.pos, robot.vel), expected);
+            }
+        }
+        let robot = Robot::new((0, 0), (-1, 2), (5, 3)).with_topology(Topology::Walls);
+        assert_eq!(robot.state_at(1), ((1, 2), (1, -2)));
+        assert_eq!(robot.state_at(2), ((2, 0), (1, 2)));
+        let robot = Robot::new((1, 2), (1, 2), (5, 3)).with_topology(Topology::Klein);
+        assert_eq!(robot.state_at(1), ((2, 1), (-1, 2)));
+        let robot = Robot::new((0, 0), (3, 4), (1, 1)).with_topology(Topology::Walls);
+        assert_eq!(robot.state_at(7), ((0, 0), (3, 4)));
+    }
+    #[test]
+    fn partitions() {
+        let map = (101, 103);
+        let mut robots = read_data("input", map);
+        move_robots(&mut robots, 100);
+        assert_eq!(calc_partition_safety(&robots, &map, (1, 1)), 500);
+        assert_eq!(calc_partition_safety(&robots, &map, (2, 2)), 211773366);
+
+        let row = (0..11).map(|x| (x, 0)).collect::<Vec<(i64, i64)>>();
+        assert_eq!(partition(&row, &(11, 1), (2, 1)), vec![5, 5]);
+        assert_eq!(partition(&row, &(11, 1), (3, 1)), vec![3, 3, 3]);
+        assert_eq!(partition(&row, &(11, 1), (4, 1)), vec![2, 2, 2, 2]);
+        assert_eq!(partition(&row[..10], &(10, 1), (2, 1)), vec![5, 5]);
+        assert_eq!(partition(&row[..9], &(9, 1), (3, 1)), vec![3, 3, 3]);
+        let grid = (0..7)
+            .flat_map(|y| (0..11).map(move |x| (x, y)))
+            .collect::<Vec<(i64, i64)>>();
+        assert_eq!(partition(&grid, &(11, 7), (3, 2)), vec![9, 9, 9, 9, 9, 9]);
     }
 }
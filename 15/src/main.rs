@@ -2,8 +2,11 @@
 // - read a warehouse map with
 //   - positions of walls (marked by #)
 //   - boxes (marked by O)
+//     - other box shapes can be defined in a legend after the map, every line
+//       names a shape and draws it with # in rows separated by /, like
+//       "L = #./##", all cells of such a box are marked by its name
 //   - initial robot position (marked by @)
-//   - a new line after the map
+//   - a new line after the map (and the legend)
 //   - robot movements after the new line
 //     - < for left
 //     - ^ for up
@@ -30,237 +33,527 @@
 //   - pushing one box vertically might now affect multiple boxes horizontally
 //   - the GPS position calculation doesn't change, the box edge closest to the
 //     map edge is used for calculation
+// - the warehouse is stored as a dense grid, a push collects all affected box
+//   cells with a breadth first search and moves them at once, or nothing when
+//   one of them would hit a wall
+// - the map can be widened by any factor, every cell becomes that many cells
+//   and boxes become as much wider
+// - the GPS position of a box is taken from a configurable anchor cell
+// - movements can be replayed one at a time and undone, every state can be
+//   rendered in the map format of the puzzle, run with
+//   `states <map file> [factor]` to print them
+// - the robot can be driven interactively in a terminal, run with
+//   `play <map file> [factor] [output file]`
+//   - arrow keys move the robot, u (or backspace) undoes and r redoes a move,
+//     q (or escape) quits
+//   - the typed movements are saved with the map in the input format, or
+//     printed when no output file is given
 
-use std::collections::HashMap;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue, style::Print};
+use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::io::{self, Write};
 use std::iter::FromIterator;
 
-type PositionMap = HashMap<Type, Vec<Position>>;
-
 fn main() {
-    let (mut positions, mut movements) = read_data("input", false);
-    let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-    moves(&mut robot, &mut positions, &mut movements, false);
-    let sum = sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), false);
+    let args = std::env::args().collect::<Vec<String>>();
+    if args.get(1).map(|a| a.as_str()) == Some("play") {
+        let filename = args.get(2).map_or("input.test", |a| a.as_str());
+        let factor = args
+            .get(3)
+            .map_or(1, |a| a.parse().expect("Invalid factor"));
+        let (map, legend) = read_map(filename);
+        let lines = map
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+        let warehouse = parse_map(&lines, &parse_legend(&legend), factor);
+        let movements = play(warehouse).expect("Terminal error");
+        let mut recording = vec![map, legend, movement_string(&movements)];
+        recording.retain(|p| !p.is_empty());
+        let recording = recording.join("\n\n") + "\n";
+        match args.get(4) {
+            Some(output) => {
+                std::fs::write(output, recording).expect("Can't write recording");
+                println!("{} movements were written to {}", movements.len(), output);
+            }
+            None => print!("{}", recording),
+        }
+        return;
+    }
+    if args.get(1).map(|a| a.as_str()) == Some("states") {
+        let filename = args.get(2).map_or("input.test", |a| a.as_str());
+        let factor = args
+            .get(3)
+            .map_or(1, |a| a.parse().expect("Invalid factor"));
+        let (warehouse, movements) = read_scaled_data(filename, factor);
+        println!("{}", transcript(&warehouse, &movements));
+        return;
+    }
+
+    let (mut warehouse, movements) = read_data("input", false);
+    moves(&mut warehouse, &movements);
+    let sum = sum_coordinates(&warehouse.packages, Anchor::FirstCell);
     println!("The summed GPS positions are {}", sum);
 
-    let (mut positions, mut movements) = read_data("input", true);
-    let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-    moves(&mut robot, &mut positions, &mut movements, true);
-    let sum = sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), true);
+    let (mut warehouse, movements) = read_data("input", true);
+    moves(&mut warehouse, &movements);
+    let sum = sum_coordinates(&warehouse.packages, Anchor::FirstCell);
     println!("The summed GPS positions in the wide warehouse are {}", sum);
+
+    let (mut warehouse, movements) = read_scaled_data("input2.test", 3);
+    moves(&mut warehouse, &movements);
+    for anchor in [Anchor::FirstCell, Anchor::TopLeft, Anchor::Center] {
+        let sum = sum_coordinates(&warehouse.packages, anchor);
+        println!(
+            "The summed {:?} GPS positions in the warehouse with shaped boxes are {}",
+            anchor, sum
+        );
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Direction {
     Left,
     Down,
     Up,
     Right,
-    None,
 }
 
+impl Direction {
+    fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Right => Direction::Left,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+        }
+    }
+    fn symbol(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Right => '>',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+        }
+    }
+    // the position next to x, y in this direction
+    fn next(&self, x: usize, y: usize) -> (usize, usize) {
+        match self {
+            Direction::Up => (x, y - 1),
+            Direction::Right => (x + 1, y),
+            Direction::Down => (x, y + 1),
+            Direction::Left => (x - 1, y),
+        }
+    }
+}
+
+// content of a cell, boxes have the index of their package
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 enum Type {
     Wall,
-    Box,
-    BoxL,
-    BoxR,
+    Box(usize),
     Robot,
     Empty,
 }
 
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
-struct Position {
-    x: usize,
-    y: usize,
-    typ: Type,
+// a box shape from the legend, the cell offsets are relative to the first
+// cell of the top row
+#[derive(Clone, Debug, PartialEq)]
+struct Shape {
+    name: char,
+    cells: Vec<(i64, i64)>,
 }
 
-impl Position {
-    fn new(x: usize, y: usize, typ: Type) -> Self {
-        Self { x, y, typ }
+impl Shape {
+    // parse a shape like "#./##"
+    fn new(name: char, pattern: &str) -> Self {
+        let mut cells = Vec::new();
+        for (y, row) in pattern.split('/').enumerate() {
+            for (x, c) in row.trim().chars().enumerate() {
+                match c {
+                    '#' => cells.push((x as i64, y as i64)),
+                    '.' => (),
+                    _ => panic!("Unknown shape symbol {} in {}", c, pattern),
+                }
+            }
+        }
+        assert!(!cells.is_empty(), "Shape {} is empty", name);
+        let first = cells[0];
+        let cells = cells
+            .iter()
+            .map(|(x, y)| (x - first.0, y - first.1))
+            .collect();
+        Self { name, cells }
     }
-    fn step(&mut self, direction: &Direction) {
-        match direction {
-            Direction::Up => self.y -= 1,
-            Direction::Right => self.x += 1,
-            Direction::Down => self.y += 1,
-            Direction::Left => self.x -= 1,
-            Direction::None => panic!("Unkonw direction"),
-        };
+}
+
+// a box in the warehouse with the shape it was created from and the cells it
+// covers, row by row
+#[derive(Clone, Debug, PartialEq)]
+struct Package {
+    shape: char,
+    cells: Vec<(usize, usize)>,
+}
+
+impl Package {
+    fn new(shape: char, mut cells: Vec<(usize, usize)>) -> Self {
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        Self { shape, cells }
     }
-    fn new_pos(&self, direction: &Direction, positions: &PositionMap) -> Self {
-        let mut pos = Position::new(self.x, self.y, self.typ);
-        match direction {
-            Direction::Up => pos.y -= 1,
-            Direction::Right => pos.x += 1,
-            Direction::Down => pos.y += 1,
-            Direction::Left => pos.x -= 1,
-            Direction::None => panic!("Unkonw direction"),
-        };
-        if let Some(found) = positions
-            .values()
-            .flatten()
-            .find(|p| p.x == pos.x && p.y == pos.y)
-        {
-            pos.typ = found.typ;
-        } else {
-            pos.typ = Type::Empty;
+}
+
+// the cell of a box that's used for its GPS position
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Anchor {
+    // the leftmost cell of the top row, the box edge closest to the map edge
+    // for the puzzle boxes
+    FirstCell,
+    // the top left corner of the bounding box, might not be part of the box
+    TopLeft,
+    // the center of the bounding box, rounded down
+    Center,
+}
+
+impl Anchor {
+    fn position(&self, package: &Package) -> (usize, usize) {
+        let xs = package.cells.iter().map(|c| c.0);
+        let ys = package.cells.iter().map(|c| c.1);
+        let (x0, x1) = (xs.clone().min().unwrap(), xs.max().unwrap());
+        let (y0, y1) = (ys.clone().min().unwrap(), ys.max().unwrap());
+        match self {
+            Anchor::FirstCell => package.cells[0],
+            Anchor::TopLeft => (x0, y0),
+            Anchor::Center => ((x0 + x1) / 2, (y0 + y1) / 2),
         }
-        pos
-    }
-    fn other_box(&self, positions: &PositionMap) -> Option<Self> {
-        let pos = if self.typ == Type::BoxL {
-            match positions
-                .values()
-                .flatten()
-                .find(|p| p.x == self.x + 1 && p.y == self.y)
-            {
-                Some(p) => assert_eq!(p.typ, Type::BoxR),
-                None => panic!("Neighbor box not found"),
-            };
-            Some(Position::new(self.x + 1, self.y, Type::BoxR))
-        } else if self.typ == Type::BoxR {
-            match positions
-                .values()
-                .flatten()
-                .find(|p| p.x == self.x - 1 && p.y == self.y)
-            {
-                Some(p) => assert_eq!(p.typ, Type::BoxL),
-                None => panic!("Neighbor box not found"),
-            };
-            Some(Position::new(self.x - 1, self.y, Type::BoxL))
-        } else {
-            None
-        };
-        pos
     }
 }
 
-// execute all movements
-fn moves(
-    robot: &mut Position,
-    positions: &mut PositionMap,
-    movements: &mut Vec<Direction>,
-    part2: bool,
-) {
-    while let Some(direction) = movements.pop() {
-        let mut moveables = get_moves(robot, &direction, positions);
-        // part 2 differs only in vertical movement and when boxes are affected
-        if part2
-            && (direction == Direction::Up || direction == Direction::Down)
-            && moveables.len() > 1
-        {
-            let robot = moveables.pop().expect("No last moveable");
-            assert_eq!(robot.typ, Type::Robot);
-            loop {
-                let (new_dangling, movs) = recalculate_moveables(&moveables, &direction, positions);
-                moveables = movs;
-                if !new_dangling {
-                    break;
+// the warehouse as a grid of cells, row by row
+// - the map is surrounded by walls, so moving never leaves the grid
+#[derive(Clone, Debug)]
+struct Warehouse {
+    width: usize,
+    height: usize,
+    cells: Vec<Type>,
+    robot: (usize, usize),
+    packages: Vec<Package>,
+}
+
+impl Warehouse {
+    fn get(&self, x: usize, y: usize) -> Type {
+        self.cells[y * self.width + x]
+    }
+    fn set(&mut self, x: usize, y: usize, typ: Type) {
+        self.cells[y * self.width + x] = typ;
+    }
+    // move the robot one step, pushing all boxes in the way
+    fn push(&mut self, direction: Direction) -> bool {
+        self.push_packages(direction).is_some()
+    }
+    // move the robot one step and get the boxes it pushed
+    // - every cell that has to move is visited once, a box adds all of its
+    //   cells, and each moving cell pushes the cell in front of it
+    // - returns None when a wall blocks the push, nothing is moved then
+    fn push_packages(&mut self, direction: Direction) -> Option<Vec<usize>> {
+        let mut packages = Vec::new();
+        let mut seen = HashSet::from([self.robot]);
+        let mut queue = VecDeque::from([self.robot]);
+        while let Some((x, y)) = queue.pop_front() {
+            let (nx, ny) = direction.next(x, y);
+            if seen.contains(&(nx, ny)) {
+                continue;
+            }
+            match self.get(nx, ny) {
+                Type::Wall => return None,
+                Type::Box(id) => {
+                    packages.push(id);
+                    for cell in &self.packages[id].cells {
+                        if seen.insert(*cell) {
+                            queue.push_back(*cell);
+                        }
+                    }
                 }
+                _ => (),
             }
-            if !moveables.is_empty() {
-                moveables.push(robot);
+        }
+        self.shift(&packages, direction);
+        Some(packages)
+    }
+    // move the robot and the given boxes one step, undoing a push shifts them
+    // back in the opposite direction
+    fn shift(&mut self, packages: &[usize], direction: Direction) {
+        self.set(self.robot.0, self.robot.1, Type::Empty);
+        for id in packages {
+            for (x, y) in self.packages[*id].cells.clone() {
+                self.set(x, y, Type::Empty);
             }
         }
-        for pos in &moveables {
-            if pos.typ != Type::Robot {
-                let mbox = positions
-                    .get_mut(&Type::Box)
-                    .expect("No boxes found")
-                    .iter_mut()
-                    .find(|p| p.x == pos.x && p.y == pos.y && p.typ == pos.typ)
-                    .expect("Position not in boxes");
-                mbox.step(&direction);
-            } else {
-                robot.step(&direction);
+        for id in packages {
+            for cell in self.packages[*id].cells.iter_mut() {
+                *cell = direction.next(cell.0, cell.1);
+            }
+            for (x, y) in self.packages[*id].cells.clone() {
+                self.set(x, y, Type::Box(*id));
             }
         }
+        self.robot = direction.next(self.robot.0, self.robot.1);
+        self.set(self.robot.0, self.robot.1, Type::Robot);
+    }
+    // render the warehouse like the puzzle does
+    // - puzzle boxes are O, or [] when they are wider, with - between the
+    //   brackets when they are wider than two cells
+    // - other boxes are marked by their shape name
+    fn render(&self) -> String {
+        let mut lines = Vec::new();
+        for y in 0..self.height {
+            let line = (0..self.width).map(|x| match self.get(x, y) {
+                Type::Wall => '#',
+                Type::Robot => '@',
+                Type::Empty => '.',
+                Type::Box(id) => {
+                    let package = &self.packages[id];
+                    let row = package.cells.iter().filter(|c| c.1 == y);
+                    let (first, last) = (row.clone().map(|c| c.0).min(), row.map(|c| c.0).max());
+                    match package.shape {
+                        'O' if first == last => 'O',
+                        'O' if first == Some(x) => '[',
+                        'O' if last == Some(x) => ']',
+                        'O' => '-',
+                        shape => shape,
+                    }
+                }
+            });
+            lines.push(String::from_iter(line));
+        }
+        lines.join("\n")
     }
 }
 
-// get all moves that are required when the robot moves
-// moving the robot might involve moving several boxes
-fn get_moves(robot: &Position, direction: &Direction, positions: &PositionMap) -> Vec<Position> {
-    let mut pos = *robot;
-    let mut moveables = Vec::new();
-    loop {
-        pos = pos.new_pos(direction, positions);
-        if pos.typ == Type::Empty || pos.typ == Type::Wall {
-            break;
-        } else {
-            moveables.push(pos);
+// step through movements one at a time
+#[derive(Clone, Debug)]
+struct Replay {
+    warehouse: Warehouse,
+    movements: Vec<Direction>,
+    // the boxes pushed by every executed movement, None if the robot was
+    // blocked
+    history: Vec<Option<Vec<usize>>>,
+}
+
+impl Replay {
+    fn new(warehouse: Warehouse, movements: &[Direction]) -> Self {
+        Self {
+            warehouse,
+            movements: movements.to_vec(),
+            history: Vec::new(),
         }
     }
-    if pos.typ == Type::Wall {
-        return Vec::new();
+    // execute the next movement
+    fn step(&mut self) -> Option<Direction> {
+        let direction = *self.movements.get(self.history.len())?;
+        let pushed = self.warehouse.push_packages(direction);
+        self.history.push(pushed);
+        Some(direction)
+    }
+    // execute a new movement, movements that were undone are dropped
+    // - returns false when the robot was blocked
+    fn apply(&mut self, direction: Direction) -> bool {
+        self.movements.truncate(self.history.len());
+        self.movements.push(direction);
+        self.step();
+        self.history.last().is_some_and(|p| p.is_some())
+    }
+    // the movements that were executed so far
+    fn executed(&self) -> &[Direction] {
+        &self.movements[..self.history.len()]
+    }
+    // revert the last executed movement, it can be executed again by step
+    fn undo(&mut self) -> Option<Direction> {
+        let pushed = self.history.pop()?;
+        let direction = self.movements[self.history.len()];
+        if let Some(packages) = pushed {
+            self.warehouse.shift(&packages, direction.opposite());
+        }
+        Some(direction)
+    }
+}
+
+// the movements with the warehouse state after each of them
+impl Iterator for Replay {
+    type Item = (Direction, Warehouse);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let direction = self.step()?;
+        Some((direction, self.warehouse.clone()))
+    }
+}
+
+// render every state of the warehouse like the puzzle does
+fn transcript(warehouse: &Warehouse, movements: &[Direction]) -> String {
+    let mut states = vec![format!("Initial state:\n{}", warehouse.render())];
+    for (direction, state) in Replay::new(warehouse.clone(), movements) {
+        states.push(format!("Move {}:\n{}", direction.symbol(), state.render()));
+    }
+    states.join("\n\n")
+}
+
+// execute all movements
+fn moves(warehouse: &mut Warehouse, movements: &[Direction]) {
+    for direction in movements {
+        warehouse.push(*direction);
     }
-    moveables.push(*robot);
-    moveables
 }
 
 // calculate the sum of all GPS positions
-fn sum_coordinates(positions: &[Position], part2: bool) -> usize {
-    if !part2 {
-        positions.iter().map(|p| p.y * 100 + p.x).sum()
-    } else {
-        positions
-            .iter()
-            .filter(|p| p.typ == Type::BoxL)
-            .map(|p| p.y * 100 + p.x)
-            .sum()
-    }
-}
-
-// check all affected boxes on vertical movement in part 2
-fn recalculate_moveables(
-    old_moveables: &[Position],
-    direction: &Direction,
-    positions: &PositionMap,
-) -> (bool, Vec<Position>) {
-    let mut new_dangling = false;
-    let mut vd_moveables: VecDeque<Position> = VecDeque::from_iter(old_moveables.to_owned());
-    let mut moveables = Vec::new();
-    while let Some(moveable) = vd_moveables.pop_front() {
-        let neighbor = moveable
-            .other_box(positions)
-            .expect("Neighbor box not found");
-        if !old_moveables.contains(&neighbor) {
-            moveables.push(neighbor);
-            new_dangling = true;
-        }
-        let new_neighbor = neighbor.new_pos(direction, positions);
-        if (new_neighbor.typ == Type::BoxL || new_neighbor.typ == Type::BoxR)
-            && !old_moveables.contains(&new_neighbor)
-        {
-            moveables.push(new_neighbor);
-            let neighbor = new_neighbor
-                .other_box(positions)
-                .expect("Neighbor box not found");
-            if !old_moveables.contains(&neighbor) {
-                moveables.push(neighbor);
+fn sum_coordinates(packages: &[Package], anchor: Anchor) -> usize {
+    packages
+        .iter()
+        .map(|p| anchor.position(p))
+        .map(|(x, y)| y * 100 + x)
+        .sum()
+}
+
+// what a key does in the interactive mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Move(Direction),
+    Undo,
+    Redo,
+    Quit,
+}
+
+fn action(code: KeyCode) -> Option<Action> {
+    match code {
+        KeyCode::Left => Some(Action::Move(Direction::Left)),
+        KeyCode::Right => Some(Action::Move(Direction::Right)),
+        KeyCode::Up => Some(Action::Move(Direction::Up)),
+        KeyCode::Down => Some(Action::Move(Direction::Down)),
+        KeyCode::Char('u') | KeyCode::Backspace => Some(Action::Undo),
+        KeyCode::Char('r') => Some(Action::Redo),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+// the lines shown in the interactive mode
+// - the warehouse, the GPS sum and the latest movements on the undo stack
+fn screen(replay: &Replay, message: &str) -> Vec<String> {
+    let mut lines = replay
+        .warehouse
+        .render()
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>();
+    let executed = movement_string(replay.executed()).replace('\n', "");
+    let recent = executed.chars().skip(executed.len().saturating_sub(40));
+    lines.push(String::new());
+    lines.push(format!(
+        "GPS sum: {}",
+        sum_coordinates(&replay.warehouse.packages, Anchor::FirstCell)
+    ));
+    lines.push(format!(
+        "Undo stack ({}): {}",
+        executed.len(),
+        String::from_iter(recent)
+    ));
+    lines.push(format!(
+        "Redo: {}",
+        replay.movements.len() - replay.executed().len()
+    ));
+    lines.push(String::new());
+    lines.push("arrows: move, u: undo, r: redo, q: quit".to_string());
+    lines.push(message.to_string());
+    lines
+}
+
+// drive the robot through the warehouse in the terminal, get the executed
+// movements
+fn play(warehouse: Warehouse) -> io::Result<Vec<Direction>> {
+    let mut replay = Replay::new(warehouse, &[]);
+    let mut stdout = io::stdout();
+    let _terminal = Terminal::new()?;
+    let mut message = String::new();
+    loop {
+        draw(&mut stdout, &screen(&replay, &message))?;
+        let key = match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => key,
+            _ => continue,
+        };
+        message = match action(key.code) {
+            Some(Action::Move(direction)) if !replay.apply(direction) => {
+                format!("The robot is blocked to the {:?}", direction)
             }
-            new_dangling = true;
-        }
-        moveables.push(moveable);
+            Some(Action::Undo) if replay.undo().is_none() => "Nothing to undo".to_string(),
+            Some(Action::Redo) if replay.step().is_none() => "Nothing to redo".to_string(),
+            Some(Action::Quit) => break,
+            _ => String::new(),
+        };
     }
-    moveables.sort();
-    moveables.dedup();
-    for moveable in &moveables {
-        if moveable.new_pos(direction, positions).typ == Type::Wall {
-            return (false, Vec::new());
-        }
+    Ok(replay.executed().to_vec())
+}
+
+// the terminal in raw mode on the alternate screen, it's restored when this
+// is dropped, also on errors and panics
+struct Terminal;
+
+impl Terminal {
+    fn new() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let terminal = Terminal;
+        execute!(io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(terminal)
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // restore as much as possible, there's no way to report errors here
+        let _ = execute!(io::stdout(), cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
     }
-    (new_dangling, moveables)
 }
 
-// read the warehouse information
-fn read_data(filename: &str, part2: bool) -> (PositionMap, Vec<Direction>) {
+// draw the screen lines from the top left corner
+fn draw(stdout: &mut io::Stdout, lines: &[String]) -> io::Result<()> {
+    queue!(stdout, Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+    for line in lines {
+        queue!(stdout, Print(line), Print("\r\n"))?;
+    }
+    stdout.flush()
+}
+
+// write movements like the puzzle input, 70 per line
+fn movement_string(movements: &[Direction]) -> String {
+    movements
+        .chunks(70)
+        .map(|c| c.iter().map(|d| d.symbol()).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// read the map and the legend of a warehouse file, movements are ignored
+fn read_map(filename: &str) -> (String, String) {
+    let file = std::fs::read_to_string(filename).expect("Can't read input");
+    let parts = file.trim().split("\n\n").collect::<Vec<&str>>();
+    let legend = match parts.get(1) {
+        Some(legend) if legend.contains('=') => legend.to_string(),
+        _ => String::new(),
+    };
+    (parts[0].to_string(), legend)
+}
+
+// read the warehouse information, with boxes twice as wide for part 2
+fn read_data(filename: &str, part2: bool) -> (Warehouse, Vec<Direction>) {
+    read_scaled_data(filename, if part2 { 2 } else { 1 })
+}
+
+// read the warehouse information, with everything `factor` times as wide
+fn read_scaled_data(filename: &str, factor: usize) -> (Warehouse, Vec<Direction>) {
     let file = std::fs::read_to_string(filename).expect("Can't read input");
     let parts = file.trim().split("\n\n").collect::<Vec<&str>>();
-    assert_eq!(parts.len(), 2);
+    assert!(parts.len() <= 3);
     let mut map = Vec::new();
     for line in parts[0].split("\n") {
         if line.is_empty() {
@@ -268,108 +561,109 @@ fn read_data(filename: &str, part2: bool) -> (PositionMap, Vec<Direction>) {
         }
         map.push(line.chars().collect::<Vec<char>>());
     }
-    let movements = parts[1]
-        .chars()
-        .rev()
-        .filter(|&c| c == '>' || c == '<' || c == 'v' || c == '^')
-        .map(|c| match c {
-            '>' => Direction::Right,
-            '<' => Direction::Left,
-            '^' => Direction::Up,
-            'v' => Direction::Down,
-            _ => Direction::None,
+    // the legend and the movements are optional, a recording without any
+    // movements only has the map
+    let legend = parse_legend(parts[1..].iter().find(|p| p.contains('=')).unwrap_or(&""));
+    let movements = parts[1..]
+        .iter()
+        .filter(|p| !p.contains('='))
+        .flat_map(|p| p.chars())
+        .filter_map(|c| match c {
+            '>' => Some(Direction::Right),
+            '<' => Some(Direction::Left),
+            '^' => Some(Direction::Up),
+            'v' => Some(Direction::Down),
+            _ => None,
         })
         .collect::<Vec<Direction>>();
-    let positions = parse_map(&map, part2);
-    assert!(positions.contains_key(&Type::Robot));
-    assert_eq!(positions.get(&Type::Robot).unwrap().len(), 1);
-    (positions, movements)
+    (parse_map(&map, &legend, factor), movements)
 }
 
-// create a HashMap from the map with robot, wall and box positions
-fn parse_map(map: &[Vec<char>], part2: bool) -> PositionMap {
-    let mut positions = HashMap::new();
+// read the box shapes, the puzzle box O is always known
+fn parse_legend(legend: &str) -> Vec<Shape> {
+    let mut shapes = vec![Shape::new('O', "#")];
+    for line in legend.lines().filter(|l| !l.trim().is_empty()) {
+        let (name, pattern) = line.split_once('=').expect("Legend line needs a =");
+        let mut name = name.trim().chars();
+        let shape = match (name.next(), name.next()) {
+            (Some(c), None) if !"#.@<>^v".contains(c) => Shape::new(c, pattern.trim()),
+            _ => panic!("Invalid shape name in {}", line),
+        };
+        shapes.retain(|s| s.name != shape.name);
+        shapes.push(shape);
+    }
+    shapes
+}
+
+// create the warehouse grid from the map with robot, wall and box positions
+// - the cells of a box are marked by its shape name, boxes are found from
+//   the top left, where the first unassigned cell is the first cell of a box
+// - every map cell becomes `factor` cells, the robot takes the first of them
+fn parse_map(map: &[Vec<char>], legend: &[Shape], factor: usize) -> Warehouse {
+    let height = map.len();
+    let width = map.iter().map(|l| l.len()).max().unwrap_or(0) * factor;
+    let mut cells = vec![Type::Empty; width * height];
+    let mut robots = Vec::new();
+    let mut packages = Vec::new();
     (0..map.len()).for_each(|y| {
         (0..map[y].len()).for_each(|x| {
-            if !part2 {
-                let pos = match map[y][x] {
-                    '#' => Position::new(x, y, Type::Wall),
-                    '@' => Position::new(x, y, Type::Robot),
-                    'O' => Position::new(x, y, Type::Box),
-                    _ => Position::new(x, y, Type::Empty),
-                };
-                if pos.typ != Type::Empty {
-                    positions
-                        .entry(pos.typ)
-                        .and_modify(|p: &mut Vec<Position>| p.push(pos))
-                        .or_insert(vec![pos]);
+            let cell = y * width + x * factor;
+            match map[y][x] {
+                '#' => cells[cell..cell + factor].fill(Type::Wall),
+                '@' => {
+                    cells[cell] = Type::Robot;
+                    robots.push((x * factor, y));
                 }
-            } else {
-                let position = match map[y][x] {
-                    '#' => vec![
-                        Position::new(x * 2, y, Type::Wall),
-                        Position::new(x * 2 + 1, y, Type::Wall),
-                    ],
-                    '@' => vec![
-                        Position::new(x * 2, y, Type::Robot),
-                        Position::new(x * 2 + 1, y, Type::Empty),
-                    ],
-                    'O' => vec![
-                        Position::new(x * 2, y, Type::BoxL),
-                        Position::new(x * 2 + 1, y, Type::BoxR),
-                    ],
-                    _ => vec![
-                        Position::new(x * 2, y, Type::Empty),
-                        Position::new(x * 2 + 1, y, Type::Empty),
-                    ],
-                };
-                for pos in position {
-                    if pos.typ != Type::Empty {
-                        let pos_type = if pos.typ == Type::BoxL || pos.typ == Type::BoxR {
-                            Type::Box
-                        } else {
-                            pos.typ
-                        };
-                        positions
-                            .entry(pos_type)
-                            .and_modify(|p: &mut Vec<Position>| p.push(pos))
-                            .or_insert(vec![pos]);
+                '.' => (),
+                c if cells[cell] == Type::Empty => {
+                    let shape = legend
+                        .iter()
+                        .find(|s| s.name == c)
+                        .unwrap_or_else(|| panic!("Unknown map symbol {}", c));
+                    let id = packages.len();
+                    let mut covered = Vec::new();
+                    for (dx, dy) in &shape.cells {
+                        let (bx, by) = (x as i64 + dx, y as i64 + dy);
+                        let fits = by >= 0
+                            && (by as usize) < height
+                            && bx >= 0
+                            && (bx as usize) < map[by as usize].len()
+                            && map[by as usize][bx as usize] == c;
+                        assert!(fits, "Box {} at {},{} doesn't match its shape", c, x, y);
+                        let (bx, by) = (bx as usize, by as usize);
+                        for i in 0..factor {
+                            let cell = by * width + bx * factor + i;
+                            assert_eq!(cells[cell], Type::Empty, "Boxes {} overlap", c);
+                            cells[cell] = Type::Box(id);
+                            covered.push((bx * factor + i, by));
+                        }
                     }
+                    packages.push(Package::new(c, covered));
                 }
+                _ => (),
             }
         })
     });
-    assert_eq!(
-        positions.get(&Type::Robot).expect("Robot not found").len(),
-        1
-    );
-    positions
-}
-
-#[allow(dead_code)]
-fn print_map(positions: &PositionMap, robot: &Position, x: usize, y: usize) {
-    for i in 0..y {
-        let mut line = vec!['.'; x];
-        let ps = positions
-            .values()
-            .flatten()
-            .filter(|p| p.y == i)
-            .copied()
-            .collect::<Vec<Position>>();
-        for p in ps {
-            match p.typ {
-                Type::Wall => line[p.x] = '#',
-                Type::BoxL => line[p.x] = '[',
-                Type::BoxR => line[p.x] = ']',
-                Type::Robot => line[p.x] = '@',
-                Type::Empty => line[p.x] = ' ',
-                Type::Box => line[p.x] = '+',
-            }
-        }
-        if robot.y == i {
-            line[robot.x] = '@';
-        }
-        println!("{:?}", String::from_iter(line));
+    assert_eq!(robots.len(), 1, "There needs to be exactly one robot");
+    // moving never leaves the grid, as long as it's surrounded by walls
+    let border = (0..width)
+        .flat_map(|x| [(x, 0), (x, height - 1)])
+        .chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+    for (x, y) in border {
+        assert_eq!(
+            cells[y * width + x],
+            Type::Wall,
+            "The map needs a wall border, {},{} isn't a wall",
+            x,
+            y
+        );
+    }
+    Warehouse {
+        width,
+        height,
+        cells,
+        robot: robots[0],
+        packages,
     }
 }
 
@@ -377,65 +671,302 @@ fn print_map(positions: &PositionMap, robot: &Position, x: usize, y: usize) {
 mod tests {
     use super::*;
 
+    // get a row of the rendered warehouse
+    fn row(warehouse: &Warehouse, y: usize) -> String {
+        warehouse.render().lines().nth(y).unwrap().to_string()
+    }
+
+    fn to_map(lines: &[&str]) -> Vec<Vec<char>> {
+        lines.iter().map(|l| l.chars().collect()).collect()
+    }
+
     #[test]
     fn sum() {
-        let p = Position::new(4, 1, Type::Box);
-        assert_eq!(sum_coordinates(&[p], false), 104);
+        let p = Package::new('O', vec![(4, 1)]);
+        assert_eq!(sum_coordinates(&[p], Anchor::FirstCell), 104);
+        let p = Package::new('L', vec![(5, 2), (4, 3), (5, 3), (6, 3)]);
+        let packages = [p];
+        assert_eq!(sum_coordinates(&packages, Anchor::FirstCell), 205);
+        assert_eq!(sum_coordinates(&packages, Anchor::TopLeft), 204);
+        assert_eq!(sum_coordinates(&packages, Anchor::Center), 205);
     }
     #[test]
     fn part1_test() {
-        let (mut positions, mut movements) = read_data("input.test", false);
+        let (mut warehouse, movements) = read_data("input.test", false);
+        let objects = warehouse.cells.iter().filter(|c| **c != Type::Empty);
+        assert_eq!(objects.count(), 59);
+        moves(&mut warehouse, &movements);
         assert_eq!(
-            positions
-                .values()
-                .flatten()
-                .collect::<Vec<&Position>>()
-                .len(),
-            59
-        );
-        let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-        moves(&mut robot, &mut positions, &mut movements, false);
-        assert_eq!(
-            sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), false),
+            sum_coordinates(&warehouse.packages, Anchor::FirstCell),
             10092
         );
     }
     #[test]
     fn part1() {
-        let (mut positions, mut movements) = read_data("input", false);
-        let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-        moves(&mut robot, &mut positions, &mut movements, false);
+        let (mut warehouse, movements) = read_data("input", false);
+        moves(&mut warehouse, &movements);
         assert_eq!(
-            sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), false),
+            sum_coordinates(&warehouse.packages, Anchor::FirstCell),
             1360570
         );
     }
     #[test]
     fn part2_test() {
-        let (mut positions, mut movements) = read_data("input.test", true);
-        assert_eq!(
-            positions
-                .values()
-                .flatten()
-                .collect::<Vec<&Position>>()
-                .len(),
-            117
-        );
-        let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-        moves(&mut robot, &mut positions, &mut movements, true);
+        let (mut warehouse, movements) = read_data("input.test", true);
+        let objects = warehouse.cells.iter().filter(|c| **c != Type::Empty);
+        assert_eq!(objects.count(), 117);
+        moves(&mut warehouse, &movements);
         assert_eq!(
-            sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), true),
+            sum_coordinates(&warehouse.packages, Anchor::FirstCell),
             9021
         );
     }
     #[test]
     fn part2() {
-        let (mut positions, mut movements) = read_data("input", true);
-        let mut robot = positions.remove(&Type::Robot).expect("Robot not found")[0];
-        moves(&mut robot, &mut positions, &mut movements, true);
+        let (mut warehouse, movements) = read_data("input", true);
+        moves(&mut warehouse, &movements);
         assert_eq!(
-            sum_coordinates(positions.get(&Type::Box).expect("No boxes found"), true),
+            sum_coordinates(&warehouse.packages, Anchor::FirstCell),
             1381446
         );
     }
+    #[test]
+    fn pushes() {
+        // a wide box pushing two boxes, one of them blocked by a wall
+        let map = to_map(&[
+            "##############",
+            "##..........##",
+            "##..#.......##",
+            "##..WWWW....##",
+            "##...WW.....##",
+            "##....@.....##",
+            "##############",
+        ]);
+        let legend = vec![Shape::new('W', "##")];
+        let mut warehouse = parse_map(&map, &legend, 1);
+        let before = warehouse.cells.clone();
+        assert!(!warehouse.push(Direction::Up));
+        assert_eq!(warehouse.cells, before);
+        warehouse.set(4, 2, Type::Empty);
+        assert!(warehouse.push(Direction::Up));
+        assert_eq!(warehouse.robot, (6, 4));
+        assert_eq!(row(&warehouse, 2), "##..WWWW....##");
+        assert_eq!(row(&warehouse, 3), "##...WW.....##");
+        assert_eq!(row(&warehouse, 4), "##....@.....##");
+        // a row of wide boxes, pushed horizontally until the wall
+        for direction in [
+            Direction::Right,
+            Direction::Right,
+            Direction::Up,
+            Direction::Up,
+        ] {
+            assert!(warehouse.push(direction));
+        }
+        assert!(warehouse.push(Direction::Left));
+        assert!(warehouse.push(Direction::Left));
+        assert!(!warehouse.push(Direction::Left));
+        assert_eq!(warehouse.robot, (6, 2));
+        assert_eq!(row(&warehouse, 2), "##WWWW@.....##");
+    }
+    #[test]
+    fn shapes() {
+        // the T is blocked by one of its cells only, nothing moves, and the L
+        // can only move together with the T
+        let map = to_map(&[
+            "#######", "#.....#", "#.L..##", "#.LTTT#", "#.LLT.#", "#...@.#", "#######",
+        ]);
+        let legend = parse_legend("L = #./#./##\nT = ###/.#.");
+        let mut warehouse = parse_map(&map, &legend, 1);
+        assert_eq!(warehouse.packages.len(), 2);
+        let before = warehouse.cells.clone();
+        assert!(!warehouse.push(Direction::Up));
+        assert_eq!(warehouse.cells, before);
+        assert!(warehouse.push(Direction::Left));
+        assert!(warehouse.push(Direction::Left));
+        let before = warehouse.cells.clone();
+        assert!(!warehouse.push(Direction::Up));
+        assert_eq!(warehouse.cells, before);
+        warehouse.set(5, 2, Type::Empty);
+        assert!(warehouse.push(Direction::Up));
+        assert_eq!(row(&warehouse, 1), "#.L...#");
+        assert_eq!(row(&warehouse, 2), "#.LTTT#");
+        assert_eq!(row(&warehouse, 3), "#.LLT.#");
+        assert_eq!(row(&warehouse, 4), "#.@...#");
+
+        // wider than the puzzle, checked with a separate simulation
+        let expected = [
+            (1, 10092, 10092, 10092),
+            (2, 9021, 9021, 9021),
+            (3, 8512, 8512, 8533),
+        ];
+        for (factor, first, top_left, center) in expected {
+            let (mut warehouse, movements) = read_scaled_data("input.test", factor);
+            moves(&mut warehouse, &movements);
+            let packages = &warehouse.packages;
+            assert_eq!(sum_coordinates(packages, Anchor::FirstCell), first);
+            assert_eq!(sum_coordinates(packages, Anchor::TopLeft), top_left);
+            assert_eq!(sum_coordinates(packages, Anchor::Center), center);
+        }
+        let expected = [
+            (1, 1718, 1717, 1718),
+            (3, 1858, 1855, 1865),
+            (4, 1877, 1873, 1886),
+        ];
+        for (factor, first, top_left, center) in expected {
+            let (mut warehouse, movements) = read_scaled_data("input2.test", factor);
+            moves(&mut warehouse, &movements);
+            let packages = &warehouse.packages;
+            assert_eq!(sum_coordinates(packages, Anchor::FirstCell), first);
+            assert_eq!(sum_coordinates(packages, Anchor::TopLeft), top_left);
+            assert_eq!(sum_coordinates(packages, Anchor::Center), center);
+        }
+        let (mut warehouse, movements) = read_scaled_data("input2.test", 3);
+        moves(&mut warehouse, &movements);
+        assert_eq!(row(&warehouse, 1), "###..LLLLLL................###");
+        assert_eq!(row(&warehouse, 2), "###..LLL....@......TTT.....###");
+        assert_eq!(row(&warehouse, 3), "###.............TTTTTTTTT..###");
+    }
+    #[test]
+    fn replay() {
+        for (filename, part2, states) in [
+            ("input3.test", false, "states.test"),
+            ("input4.test", true, "states2.test"),
+        ] {
+            let (warehouse, movements) = read_data(filename, part2);
+            let states = std::fs::read_to_string(states).expect("Can't read states");
+            assert_eq!(transcript(&warehouse, &movements), states.trim());
+        }
+        let (warehouse, movements) = read_data("input.test", false);
+        let mut replay = Replay::new(warehouse.clone(), &movements);
+        let states = replay
+            .by_ref()
+            .map(|s| s.1.render())
+            .collect::<Vec<String>>();
+        assert_eq!(states.len(), movements.len());
+        assert_eq!(
+            states[states.len() - 1],
+            [
+                "##########",
+                "#.O.O.OOO#",
+                "#........#",
+                "#OO......#",
+                "#OO@.....#",
+                "#O#.....O#",
+                "#O.....OO#",
+                "#O.....OO#",
+                "#OO....OO#",
+                "##########",
+            ]
+            .join("\n")
+        );
+        assert_eq!(replay.step(), None);
+        // undo everything, step by step back to the start
+        for idx in (0..states.len() - 1).rev() {
+            assert_eq!(replay.undo(), Some(movements[idx + 1]));
+            assert_eq!(replay.warehouse.render(), states[idx]);
+        }
+        assert_eq!(replay.undo(), Some(movements[0]));
+        assert_eq!(replay.warehouse.render(), warehouse.render());
+        assert_eq!(replay.warehouse.cells, warehouse.cells);
+        assert_eq!(replay.undo(), None);
+        assert_eq!(replay.step(), Some(movements[0]));
+        assert_eq!(replay.warehouse.render(), states[0]);
+
+        let (warehouse, movements) = read_data("input.test", true);
+        let mut replay = Replay::new(warehouse, &movements);
+        assert_eq!(replay.by_ref().count(), movements.len());
+        assert_eq!(
+            replay.warehouse.render(),
+            [
+                "####################",
+                "##[].......[].[][]##",
+                "##[]...........[].##",
+                "##[]........[][][]##",
+                "##[]......[]....[]##",
+                "##..##......[]....##",
+                "##..[]............##",
+                "##..@......[].[][]##",
+                "##......[][]..[]..##",
+                "####################",
+            ]
+            .join("\n")
+        );
+        let (warehouse, _) = read_scaled_data("input3.test", 3);
+        assert_eq!(
+            warehouse.render().lines().nth(2).unwrap(),
+            "######@.....[-]......###"
+        );
+    }
+    #[test]
+    #[should_panic(expected = "The map needs a wall border, 3,1 isn't a wall")]
+    fn unwalled_map() {
+        let map = ["####", "#.@.", "####"]
+            .iter()
+            .map(|l| l.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+        parse_map(&map, &parse_legend(""), 1);
+    }
+    #[test]
+    fn interactive() {
+        assert_eq!(action(KeyCode::Up), Some(Action::Move(Direction::Up)));
+        assert_eq!(action(KeyCode::Backspace), Some(Action::Undo));
+        assert_eq!(action(KeyCode::Char('r')), Some(Action::Redo));
+        assert_eq!(action(KeyCode::Esc), Some(Action::Quit));
+        assert_eq!(action(KeyCode::Char('x')), None);
+
+        // type the moves of the example, with some detours that are undone
+        let (map, legend) = read_map("input3.test");
+        assert_eq!(legend, "");
+        let lines = map
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+        let warehouse = parse_map(&lines, &parse_legend(&legend), 1);
+        let (_, movements) = read_data("input3.test", false);
+        let mut replay = Replay::new(warehouse, &[]);
+        assert!(!replay.apply(Direction::Left));
+        assert!(replay.apply(Direction::Down));
+        assert!(replay.apply(Direction::Right));
+        assert_eq!(replay.undo(), Some(Direction::Right));
+        assert_eq!(replay.undo(), Some(Direction::Down));
+        assert_eq!(replay.undo(), Some(Direction::Left));
+        for direction in &movements {
+            replay.apply(*direction);
+            assert_eq!(replay.undo(), Some(*direction));
+            assert_eq!(replay.step(), Some(*direction));
+        }
+        assert_eq!(replay.executed(), movements);
+        let screen = screen(&replay, "");
+        assert_eq!(screen[4], "#.#O@..#");
+        assert_eq!(screen[9], "GPS sum: 2028");
+        assert_eq!(screen[10], "Undo stack (15): <^^>>>vv<v>>v<<");
+        assert_eq!(screen[11], "Redo: 0");
+
+        // the recording can be read again
+        let moves = (0..150)
+            .map(|i| [Direction::Up, Direction::Left][i % 2])
+            .collect::<Vec<Direction>>();
+        let recording = movement_string(&moves);
+        assert_eq!(
+            recording.lines().map(|l| l.len()).collect::<Vec<usize>>(),
+            vec![70, 70, 10]
+        );
+        let path = std::env::temp_dir().join("aoc24-15-recording.test");
+        std::fs::write(&path, format!("{}\n\n{}\n", map, recording)).unwrap();
+        let (_, read) = read_data(path.to_str().unwrap(), false);
+        assert_eq!(read, moves);
+        // a session without any movements only records the map
+        std::fs::write(&path, format!("{}\n", map)).unwrap();
+        let (read, moves) = read_data(path.to_str().unwrap(), false);
+        assert_eq!(read.render(), map);
+        assert!(moves.is_empty());
+        let (map, legend) = read_map("input2.test");
+        std::fs::write(&path, format!("{}\n\n{}\n", map, legend)).unwrap();
+        let (read, moves) = read_scaled_data(path.to_str().unwrap(), 3);
+        assert_eq!(
+            read.packages.len(),
+            read_scaled_data("input2.test", 3).0.packages.len()
+        );
+        assert!(moves.is_empty());
+    }
 }